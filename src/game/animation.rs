@@ -0,0 +1,171 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_asset_loader::AssetCollection;
+
+use super::{config::ActiveConfig, GameState};
+
+/// Frame sequences shipped in `assets/player`, as (clip, file prefix, frame count)
+const CLIP_FRAMES: [(AnimationClip, &str, usize); 5] = [
+    (
+        AnimationClip::Idle,
+        "player/2BlueWizardIdle/Chara - BlueIdle",
+        20,
+    ),
+    (
+        AnimationClip::Walk,
+        "player/2BlueWizardWalk/Chara_BlueWalk",
+        20,
+    ),
+    (
+        AnimationClip::Jump,
+        "player/2BlueWizardJump/CharaWizardJump_",
+        8,
+    ),
+    (
+        AnimationClip::Dash,
+        "player/2BlueWizardJump/Dash2/DashBlue_",
+        16,
+    ),
+    (
+        AnimationClip::DashEffect,
+        "player/2BlueWizardJump/DashEffect/BlueWizardDash_",
+        16,
+    ),
+];
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(animate_sprites);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnimationClip {
+    Idle,
+    Walk,
+    Jump,
+    Dash,
+    DashEffect,
+}
+
+impl AnimationClip {
+    /// Clips that stay on their last frame once finished
+    fn looping(self) -> bool {
+        !matches!(self, AnimationClip::Jump | AnimationClip::DashEffect)
+    }
+}
+
+/// Frames of every clip, loaded with the splash so they are ready with the menu
+pub struct Animations {
+    clips: HashMap<AnimationClip, Vec<Handle<Image>>>,
+}
+
+fn frame_paths() -> impl Iterator<Item = (AnimationClip, String)> {
+    CLIP_FRAMES.iter().flat_map(|(clip, prefix, count)| {
+        (0..*count).map(move |i| (*clip, format!("{}{:05}.png", prefix, i)))
+    })
+}
+
+// Implemented by hand, the derive needs one field per file
+impl AssetCollection for Animations {
+    fn create(world: &mut World) -> Self {
+        let asset_server = world.get_resource::<AssetServer>().unwrap();
+        let mut clips: HashMap<AnimationClip, Vec<Handle<Image>>> = HashMap::default();
+        for (clip, path) in frame_paths() {
+            clips
+                .entry(clip)
+                .or_default()
+                .push(asset_server.get_handle(path.as_str()));
+        }
+        Animations { clips }
+    }
+
+    fn load(world: &mut World) -> Vec<HandleUntyped> {
+        let asset_server = world.get_resource::<AssetServer>().unwrap();
+        frame_paths()
+            .map(|(_, path)| asset_server.load_untyped(path.as_str()))
+            .collect()
+    }
+}
+
+impl Animations {
+    pub fn frame(&self, clip: AnimationClip, frame: usize) -> Handle<Image> {
+        self.clips[&clip][frame].clone()
+    }
+
+    fn len(&self, clip: AnimationClip) -> usize {
+        self.clips[&clip].len()
+    }
+}
+
+/// Playing clip of a sprite, change `clip` with `play` to switch it
+#[derive(Component)]
+pub struct Animation {
+    clip: AnimationClip,
+    frame: usize,
+    elapsed: f32,
+}
+
+impl Animation {
+    pub fn new(clip: AnimationClip) -> Self {
+        Animation {
+            clip,
+            frame: 0,
+            elapsed: 0.,
+        }
+    }
+
+    pub fn clip(&self) -> AnimationClip {
+        self.clip
+    }
+
+    /// Start `clip` from its first frame, unless it is already playing
+    pub fn play(&mut self, clip: AnimationClip) {
+        if self.clip != clip {
+            *self = Animation::new(clip);
+        }
+    }
+
+    pub fn finished(&self, animations: &Animations) -> bool {
+        !self.clip.looping() && self.frame + 1 >= animations.len(self.clip)
+    }
+}
+
+fn animate_sprites(
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
+    animations: Option<Res<Animations>>,
+    cfg: Res<ActiveConfig>,
+    mut query: Query<(&mut Animation, &mut Handle<Image>)>,
+) {
+    // Freeze on the current frame while the run is stopped
+    if matches!(
+        game_state.current(),
+        GameState::Paused | GameState::GameOver
+    ) {
+        return;
+    }
+    // Nothing plays until the splash loaded the frames
+    let animations = match animations {
+        Some(animations) => animations,
+        None => return,
+    };
+    let frame_time = 1. / cfg.animation_fps;
+    for (mut animation, mut texture) in query.iter_mut() {
+        animation.elapsed += time.delta_seconds();
+        while animation.elapsed >= frame_time {
+            animation.elapsed -= frame_time;
+            let len = animations.len(animation.clip);
+            animation.frame = if animation.clip.looping() {
+                (animation.frame + 1) % len
+            } else {
+                (animation.frame + 1).min(len - 1)
+            };
+        }
+        let frame = animations.frame(animation.clip, animation.frame);
+        if *texture != frame {
+            *texture = frame;
+        }
+    }
+}
@@ -1,14 +1,15 @@
 use bevy::{audio::AudioSink, prelude::*};
 
-use crate::{GameConfigAsset, GameConfigController};
-
-use super::{enviroment::EnviromentAssets, GameState};
+use super::{config::ActiveConfig, enviroment::EnviromentAssets, GameSettings, GameState};
 
 pub struct AmbientAudioPlugin;
 
 impl Plugin for AmbientAudioPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(setup));
+        app.add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(setup))
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(pause_music))
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(resume_music))
+            .add_system(apply_volume);
     }
 }
 
@@ -20,18 +21,53 @@ fn setup(
     audio: Res<Audio>,
     audio_sinks: Res<Assets<AudioSink>>,
 
-    assets: Res<Assets<GameConfigAsset>>,
-    q: Res<GameConfigController>,
+    cfg: Res<ActiveConfig>,
+    settings: Res<GameSettings>,
+    music: Option<Res<MusicController>>,
 ) {
-    let cfg = assets.get(q.handle.clone()).unwrap();
-    commands.remove_resource::<MusicController>();
+    // Keep the music playing when coming back from a run
+    if music.is_some() {
+        return;
+    }
     let handle = audio_sinks.get_handle(audio.play_with_settings(
         audio_assets.background.clone(),
         PlaybackSettings {
             repeat: true,
-            volume: cfg.audio_volume,
+            volume: cfg.audio_volume * settings.music_volume,
             ..Default::default()
         },
     ));
     commands.insert_resource(MusicController(handle));
 }
+
+fn pause_music(music: Res<MusicController>, audio_sinks: Res<Assets<AudioSink>>) {
+    if let Some(sink) = audio_sinks.get(&music.0) {
+        sink.pause();
+    }
+}
+
+fn resume_music(music: Res<MusicController>, audio_sinks: Res<Assets<AudioSink>>) {
+    if let Some(sink) = audio_sinks.get(&music.0) {
+        sink.play();
+    }
+}
+
+/// `audio_volume` of the config is the mix level, scaled by the user setting
+/// Follows both the volume settings and the edits of `config.ron`
+fn apply_volume(
+    settings: Res<GameSettings>,
+    cfg: Res<ActiveConfig>,
+    music: Option<Res<MusicController>>,
+    audio_sinks: Res<Assets<AudioSink>>,
+) {
+    if !settings.is_changed() && !cfg.is_changed() {
+        return;
+    }
+    let music = match music {
+        Some(music) => music,
+        None => return,
+    };
+    if let Some(sink) = audio_sinks.get(&music.0) {
+        sink.set_volume(cfg.audio_volume * settings.music_volume);
+    }
+}
@@ -0,0 +1,280 @@
+use std::ops::Deref;
+
+use bevy::{asset::LoadState, prelude::*};
+use heron::prelude::*;
+
+use crate::{GameConfigAsset, GameConfigController};
+
+use super::{enviroment::Floor, player::PlayerSettings, view::ViewSize, GameState};
+
+pub const CONFIG_FILE: &str = "config.ron";
+const ERROR_FONT_SIZE: f32 = 24.0;
+const ERROR_COLOR: Color = Color::rgb(0.9, 0.2, 0.2);
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ActiveConfig::default())
+            .add_system_set(
+                SystemSet::on_update(GameState::Splash).with_system(detect_load_failure),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::SplashEnd).with_system(check_config))
+            .add_system_set(SystemSet::on_update(GameState::SplashEnd).with_system(recheck_config))
+            .add_system(reload_config);
+    }
+}
+
+#[derive(Component)]
+struct ConfigError;
+
+/// Last `config.ron` that passed `GameConfigAsset::validate`, the game only
+/// reads this copy so an invalid edit never reaches the running systems
+#[derive(Default)]
+pub struct ActiveConfig(GameConfigAsset);
+
+impl Deref for ActiveConfig {
+    type Target = GameConfigAsset;
+
+    fn deref(&self) -> &GameConfigAsset {
+        &self.0
+    }
+}
+
+impl GameConfigAsset {
+    pub fn gravity(&self) -> Gravity {
+        Gravity::from(Vec3::new(0.0, -9.81 * self.gravity_multiplier, 0.0))
+    }
+
+    /// Where the player starts, the first platform is placed under it
+    pub fn player_start_x(&self, view_width: f32) -> f32 {
+        -(view_width * self.player_initial_pos_x)
+    }
+
+    /// Height of the floor for a view `view_height` pixels tall
+    pub fn floor_y(&self, view_height: f32) -> f32 {
+        -(view_height * self.floor_multiplier)
+    }
+
+    /// Every value out of its range, as a readable message
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut positive = |name: &str, value: f32| {
+            if value.is_nan() || value <= 0. {
+                errors.push(format!("{} must be positive, got {}", name, value));
+            }
+        };
+        positive("gravity_multiplier", self.gravity_multiplier);
+        positive("player_size_x", self.player_size_x);
+        positive("player_size_y", self.player_size_y);
+        positive("player_box_size_x", self.player_box_size_x);
+        positive("player_box_size_y", self.player_box_size_y);
+        positive("animation_fps", self.animation_fps);
+        positive("jump_speed", self.jump_speed);
+        positive("camera_smoothing", self.camera_smoothing);
+
+        let mut not_negative = |name: &str, value: f32| {
+            if value.is_nan() || value < 0. {
+                errors.push(format!("{} can't be negative, got {}", name, value));
+            }
+        };
+        not_negative("coyote_time", self.coyote_time);
+        not_negative("jump_buffer_time", self.jump_buffer_time);
+        not_negative("camera_dead_zone", self.camera_dead_zone);
+        not_negative("camera_look_ahead", self.camera_look_ahead);
+        not_negative("invulnerability_time", self.invulnerability_time);
+        not_negative("knockback_speed", self.knockback_speed);
+        not_negative("knockback_lift", self.knockback_lift);
+
+        let mut unit = |name: &str, value: f32| {
+            if !(0. ..=1.).contains(&value) {
+                errors.push(format!("{} must be in 0..=1, got {}", name, value));
+            }
+        };
+        unit("audio_volume", self.audio_volume);
+        unit("player_initial_pos_x", self.player_initial_pos_x);
+        unit("floor_multiplier", self.floor_multiplier);
+        unit("jump_cut_multiplier", self.jump_cut_multiplier);
+
+        if self.max_health == 0 {
+            errors.push("max_health must be at least 1".to_string());
+        }
+        errors
+    }
+}
+
+/// The loader waits forever on a file that can't be read or parsed
+fn detect_load_failure(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    shown: Query<(), With<ConfigError>>,
+) {
+    if shown.is_empty() && asset_server.get_load_state(CONFIG_FILE) == LoadState::Failed {
+        show_error(
+            commands,
+            &asset_server,
+            &[format!(
+                "{} is missing or can't be parsed, see the log",
+                CONFIG_FILE
+            )],
+        );
+    }
+}
+
+/// Only a valid config lets the game reach the main menu
+fn check_config(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    assets: Res<Assets<GameConfigAsset>>,
+    q: Res<GameConfigController>,
+    mut active: ResMut<ActiveConfig>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    let errors = match assets.get(q.handle.clone()) {
+        Some(cfg) => {
+            let errors = cfg.validate();
+            if errors.is_empty() {
+                active.0 = cfg.clone();
+            }
+            errors
+        }
+        None => vec![format!("{} is not loaded", CONFIG_FILE)],
+    };
+    if errors.is_empty() {
+        game_state.set(GameState::MainMenu).unwrap();
+        return;
+    }
+    for error in errors.iter() {
+        error!("Invalid {}: {}", CONFIG_FILE, error);
+    }
+    show_error(commands, &asset_server, &errors);
+}
+
+/// Fixing the file while the error is shown lets the game go on
+fn recheck_config(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<GameConfigAsset>>,
+    asset_server: Res<AssetServer>,
+    assets: Res<Assets<GameConfigAsset>>,
+    q: Res<GameConfigController>,
+    active: ResMut<ActiveConfig>,
+    game_state: ResMut<State<GameState>>,
+    shown: Query<Entity, With<ConfigError>>,
+) {
+    let modified = events
+        .iter()
+        .any(|event| matches!(event, AssetEvent::Modified { handle } if *handle == q.handle));
+    if !modified {
+        return;
+    }
+    for entity in shown.iter() {
+        commands.entity(entity).despawn();
+    }
+    check_config(commands, asset_server, assets, q, active, game_state);
+}
+
+/// Applies the valid edits of `config.ron` to what was already spawned,
+/// the values read every frame pick them up from `ActiveConfig`
+fn reload_config(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<GameConfigAsset>>,
+    view: Res<ViewSize>,
+    assets: Res<Assets<GameConfigAsset>>,
+    q: Option<Res<GameConfigController>>,
+    mut active: ResMut<ActiveConfig>,
+    mut player: Query<(&mut Sprite, &mut CollisionShape), With<PlayerSettings>>,
+    mut floor: Query<&mut Transform, With<Floor>>,
+) {
+    let q = match q {
+        Some(q) => q,
+        None => return,
+    };
+    let modified = events
+        .iter()
+        .any(|event| matches!(event, AssetEvent::Modified { handle } if *handle == q.handle));
+    if !modified {
+        return;
+    }
+    let cfg = match assets.get(q.handle.clone()) {
+        Some(cfg) => cfg,
+        None => return,
+    };
+    let errors = cfg.validate();
+    if !errors.is_empty() {
+        for error in errors.iter() {
+            error!("Not reloading {}: {}", CONFIG_FILE, error);
+        }
+        return;
+    }
+    info!("Reloading {}", CONFIG_FILE);
+
+    active.0 = cfg.clone();
+    commands.insert_resource(cfg.gravity());
+    for (mut sprite, mut shape) in player.iter_mut() {
+        sprite.custom_size = Some(Vec2::new(cfg.player_size_x, cfg.player_size_y));
+        *shape = CollisionShape::Cuboid {
+            half_extends: Vec2::new(cfg.player_box_size_x, cfg.player_box_size_y).extend(0.),
+            border_radius: None,
+        };
+    }
+    for mut transform in floor.iter_mut() {
+        transform.translation.y = cfg.floor_y(view.height);
+    }
+}
+
+fn show_error(mut commands: Commands, asset_server: &AssetServer, errors: &[String]) {
+    let mut message = format!("Can't start, fix {}:", CONFIG_FILE);
+    for error in errors {
+        message.push_str("\n- ");
+        message.push_str(error);
+    }
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(32.),
+                    left: Val::Px(32.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                message,
+                TextStyle {
+                    font: asset_server.load("fonts/tomorrow_night.ttf"),
+                    font_size: ERROR_FONT_SIZE,
+                    color: ERROR_COLOR,
+                },
+                Default::default(),
+            ),
+            ..default()
+        })
+        .insert(ConfigError);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(GameConfigAsset::default().validate().is_empty());
+    }
+
+    #[test]
+    fn out_of_range_values_are_reported() {
+        let cfg = GameConfigAsset {
+            audio_volume: 1.5,
+            player_size_x: 0.,
+            coyote_time: -0.1,
+            jump_speed: f32::NAN,
+            max_health: 0,
+            ..Default::default()
+        };
+        let errors = cfg.validate();
+        assert_eq!(errors.len(), 5);
+        assert!(errors.iter().any(|error| error.starts_with("audio_volume")));
+        assert!(errors.iter().any(|error| error.starts_with("jump_speed")));
+    }
+}
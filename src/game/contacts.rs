@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+use heron::prelude::*;
+
+use super::{enviroment::Layer, player::PlayerSettings};
+
+pub struct ContactsPlugin;
+
+impl Plugin for ContactsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WorldContactEvent>()
+            .add_event::<EnemyContactEvent>()
+            .add_event::<PickupContactEvent>()
+            .add_event::<HazardContactEvent>()
+            .add_system(dispatch_contacts);
+    }
+}
+
+/// The player started or stopped touching a body of the `World` layer
+pub struct WorldContactEvent {
+    pub entity: Entity,
+    pub started: bool,
+}
+
+/// The player touched an enemy
+pub struct EnemyContactEvent {
+    pub entity: Entity,
+}
+
+/// The player touched a pickup
+pub struct PickupContactEvent {
+    pub entity: Entity,
+}
+
+/// The player touched a hazard
+pub struct HazardContactEvent {
+    pub entity: Entity,
+}
+
+pub fn player_layers() -> CollisionLayers {
+    CollisionLayers::none()
+        .with_group(Layer::Player)
+        .with_masks(&[Layer::World, Layer::Enemy, Layer::Pickup, Layer::Hazard])
+}
+
+pub fn world_layers() -> CollisionLayers {
+    CollisionLayers::none()
+        .with_group(Layer::World)
+        .with_mask(Layer::Player)
+}
+
+/// Enemies only collide with the player, never with each other or the world
+pub fn enemy_layers() -> CollisionLayers {
+    CollisionLayers::none()
+        .with_group(Layer::Enemy)
+        .with_mask(Layer::Player)
+}
+
+pub fn pickup_layers() -> CollisionLayers {
+    CollisionLayers::none()
+        .with_group(Layer::Pickup)
+        .with_mask(Layer::Player)
+}
+
+pub fn hazard_layers() -> CollisionLayers {
+    CollisionLayers::none()
+        .with_group(Layer::Hazard)
+        .with_mask(Layer::Player)
+}
+
+/// Turn the raw heron events involving the player into typed events,
+/// sorted by the layer of the other body
+pub fn dispatch_contacts(
+    mut collisions: EventReader<CollisionEvent>,
+    player: Query<Entity, With<PlayerSettings>>,
+    mut world: EventWriter<WorldContactEvent>,
+    mut enemies: EventWriter<EnemyContactEvent>,
+    mut pickups: EventWriter<PickupContactEvent>,
+    mut hazards: EventWriter<HazardContactEvent>,
+) {
+    let player = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    for event in collisions.iter() {
+        let (data_1, data_2) = event.data();
+        let other = if data_1.rigid_body_entity() == player {
+            data_2
+        } else if data_2.rigid_body_entity() == player {
+            data_1
+        } else {
+            continue;
+        };
+        let entity = other.rigid_body_entity();
+        let layers = other.collision_layers();
+        let started = event.is_started();
+
+        if layers.contains_group(Layer::World) {
+            world.send(WorldContactEvent { entity, started });
+        } else if !started {
+            continue;
+        } else if layers.contains_group(Layer::Enemy) {
+            enemies.send(EnemyContactEvent { entity });
+        } else if layers.contains_group(Layer::Pickup) {
+            pickups.send(PickupContactEvent { entity });
+        } else if layers.contains_group(Layer::Hazard) {
+            hazards.send(HazardContactEvent { entity });
+        }
+    }
+}
@@ -0,0 +1,312 @@
+#![allow(clippy::type_complexity)]
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::GlobalUIAssets;
+
+use super::{
+    button_label, highlight_buttons,
+    mainmenu::{build_btn, MainMenuButton, MainMenuState, MenuScreen},
+    player::PlayerAction,
+    transition::TransitionElement,
+    Binding, GameSettings,
+};
+
+const WAITING_LABEL: &str = "...";
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Rebind::default())
+            .add_system_set(
+                SystemSet::on_enter(MainMenuState::Controls).with_system(setup_controls),
+            )
+            .add_system_set(
+                SystemSet::on_update(MainMenuState::Controls)
+                    .with_system(button_system)
+                    .with_system(highlight_buttons::<ControlsButton>)
+                    .with_system(capture_binding)
+                    .with_system(update_controls),
+            )
+            .add_system(apply_bindings);
+    }
+}
+
+/// Binding slot waiting for an input and the feedback of the last change
+#[derive(Default)]
+struct Rebind {
+    slot: Option<usize>,
+    message: String,
+}
+
+#[derive(Component)]
+struct ControlsButton {
+    action: ControlsAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlsAction {
+    /// Index in `GameSettings::bindings`
+    Rebind(usize),
+    Reset,
+}
+
+#[derive(Component)]
+struct RebindMessage;
+
+fn button_system(
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<Button>)>,
+    btn_query: Query<&ControlsButton>,
+    mut rebind: ResMut<Rebind>,
+    mut settings: ResMut<GameSettings>,
+) {
+    for (interaction, children) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let btn = match button_label(children, &btn_query) {
+            Some(btn) => btn,
+            None => continue,
+        };
+        match btn.action {
+            ControlsAction::Rebind(slot) => {
+                // Clicking the slot that is listening cancels it
+                rebind.slot = if rebind.slot == Some(slot) {
+                    None
+                } else {
+                    Some(slot)
+                };
+                rebind.message.clear();
+            }
+            ControlsAction::Reset => {
+                settings.bindings = GameSettings::default().bindings;
+                rebind.slot = None;
+                rebind.message = "Controls reset to defaults".to_string();
+            }
+        }
+    }
+}
+
+/// Assign the next key or gamepad button to the listening slot,
+/// an input used by another slot is swapped with the old one
+fn capture_binding(
+    mut rebind: ResMut<Rebind>,
+    mut settings: ResMut<GameSettings>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+) {
+    let slot = match rebind.slot {
+        Some(slot) if slot < settings.bindings.len() => slot,
+        _ => return,
+    };
+    let binding = match keys
+        .get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| {
+            buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Gamepad(button.1))
+        }) {
+        Some(binding) => binding,
+        None => return,
+    };
+
+    let (action, previous) = settings.bindings[slot];
+    rebind.message = match settings
+        .bindings
+        .iter()
+        .position(|(_, other)| *other == binding)
+    {
+        Some(other) if other != slot => {
+            let conflict = settings.bindings[other].0;
+            settings.bindings[other].1 = previous;
+            format!(
+                "{} was used by {:?}, it now uses {}",
+                binding.label(),
+                conflict,
+                previous.label()
+            )
+        }
+        _ => String::new(),
+    };
+    settings.bindings[slot] = (action, binding);
+    rebind.slot = None;
+}
+
+fn update_controls(
+    rebind: Res<Rebind>,
+    settings: Res<GameSettings>,
+    mut labels: Query<(&mut Text, &ControlsButton)>,
+    mut message: Query<&mut Text, (With<RebindMessage>, Without<ControlsButton>)>,
+) {
+    if !rebind.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for (mut text, btn) in labels.iter_mut() {
+        if let ControlsAction::Rebind(slot) = btn.action {
+            text.sections[0].value = if rebind.slot == Some(slot) {
+                WAITING_LABEL.to_string()
+            } else {
+                settings
+                    .bindings
+                    .get(slot)
+                    .map_or_else(|| "-".to_string(), |(_, binding)| binding.label())
+            };
+        }
+    }
+    for mut text in message.iter_mut() {
+        text.sections[0].value = rebind.message.clone();
+    }
+}
+
+/// Keep the input map of the player in sync with the settings
+fn apply_bindings(settings: Res<GameSettings>, mut query: Query<&mut InputMap<PlayerAction>>) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+    for mut input_map in query.iter_mut() {
+        *input_map = settings.player_ctrl();
+    }
+}
+
+fn setup_controls(
+    mut commands: Commands,
+    font_assets: Res<GlobalUIAssets>,
+    settings: Res<GameSettings>,
+    mut rebind: ResMut<Rebind>,
+) {
+    *rebind = Rebind::default();
+    let text_style = TextStyle {
+        font: font_assets.pixel_font.clone(),
+        font_size: 32.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect {
+                    top: Val::Percent(6.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            color: UiColor(Color::rgba(0., 0., 0., 0.)),
+            ..default()
+        })
+        .insert(MenuScreen {
+            screen: MainMenuState::Controls,
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect {
+                            bottom: Val::Percent(3.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        "Controls",
+                        TextStyle {
+                            font: font_assets.pixel_font.clone(),
+                            font_size: 62.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment {
+                            vertical: VerticalAlign::Center,
+                            horizontal: HorizontalAlign::Center,
+                        },
+                    ),
+                    ..default()
+                })
+                .insert(TransitionElement {
+                    color_target: Color::WHITE,
+                    ..default()
+                });
+
+            for action in PlayerAction::ALL {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        color: UiColor(Color::rgba(0., 0., 0., 0.)),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn_bundle(TextBundle {
+                            style: Style {
+                                min_size: Size::new(Val::Px(220.), Val::Auto),
+                                ..Default::default()
+                            },
+                            text: Text::with_section(
+                                format!("{:?}", action),
+                                text_style.clone(),
+                                TextAlignment::default(),
+                            ),
+                            ..default()
+                        })
+                        .insert(TransitionElement {
+                            color_target: Color::WHITE,
+                            ..default()
+                        });
+                        for (slot, (_, binding)) in settings
+                            .bindings
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, (bound, _))| bound == action)
+                        {
+                            build_btn(
+                                row,
+                                font_assets.pixel_font.clone(),
+                                &binding.label(),
+                                ControlsButton {
+                                    action: ControlsAction::Rebind(slot),
+                                },
+                            );
+                        }
+                    });
+            }
+
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: font_assets.pixel_font.clone(),
+                            font_size: 24.0,
+                            color: Color::GOLD,
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..default()
+                })
+                .insert(RebindMessage);
+
+            build_btn(
+                parent,
+                font_assets.pixel_font.clone(),
+                "Reset to defaults",
+                ControlsButton {
+                    action: ControlsAction::Reset,
+                },
+            );
+            build_btn(
+                parent,
+                font_assets.pixel_font.clone(),
+                "Back",
+                MainMenuButton {
+                    action: MainMenuState::Options,
+                },
+            );
+        });
+}
@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use heron::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use super::{
+    animation::{Animation, AnimationClip, Animations},
+    player::{PlayerAction, PlayerMotion, PlayerSettings},
+    timestep::{finished_timer, FixedUpdate, TimeScale},
+    GameState, RunElement,
+};
+
+const DASH_SPEED: f32 = 900.0;
+const DASH_SECS: f32 = 0.2;
+const DASH_COOLDOWN_SECS: f32 = 1.0;
+
+pub struct DashPlugin;
+
+impl Plugin for DashPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(start_dash)
+                .with_system(despawn_effects),
+        )
+        .add_system_set_to_stage(
+            FixedUpdate,
+            SystemSet::on_update(GameState::InGame).with_system(update_dash),
+        );
+    }
+}
+
+/// Dash state of the player, both timers start finished
+#[derive(Component)]
+pub struct PlayerDash {
+    active: Timer,
+    cooldown: Timer,
+    direction: f32,
+}
+
+impl Default for PlayerDash {
+    fn default() -> Self {
+        PlayerDash {
+            active: finished_timer(DASH_SECS),
+            cooldown: finished_timer(DASH_COOLDOWN_SECS),
+            direction: 1.,
+        }
+    }
+}
+
+impl PlayerDash {
+    /// Enemies can't hurt the player while dashing
+    pub fn invulnerable(&self) -> bool {
+        !self.active.finished()
+    }
+}
+
+/// Trail left where a dash started, removed when its animation ends
+#[derive(Component)]
+struct DashEffect;
+
+fn start_dash(
+    mut commands: Commands,
+    animations: Res<Animations>,
+    mut query: Query<
+        (
+            &ActionState<PlayerAction>,
+            &Transform,
+            &Sprite,
+            &mut PlayerDash,
+            &mut PlayerMotion,
+        ),
+        With<PlayerSettings>,
+    >,
+) {
+    for (action, transform, sprite, mut dash, mut motion) in query.iter_mut() {
+        if !action.just_pressed(PlayerAction::Dash) || !dash.cooldown.finished() {
+            continue;
+        }
+        dash.direction = if action.pressed(PlayerAction::MoveLeft) {
+            -1.
+        } else {
+            1.
+        };
+        dash.active.reset();
+        dash.cooldown.reset();
+        *motion = PlayerMotion::Dashing;
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: animations.frame(AnimationClip::DashEffect, 0),
+                transform: Transform::from_translation(
+                    transform.translation - Vec3::new(0., 0., 0.1),
+                ),
+                sprite: Sprite {
+                    custom_size: sprite.custom_size,
+                    flip_x: dash.direction < 0.,
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Animation::new(AnimationClip::DashEffect))
+            .insert(DashEffect)
+            .insert(RunElement);
+    }
+}
+
+fn update_dash(
+    time_scale: Res<TimeScale>,
+    mut query: Query<(&mut PlayerDash, &mut PlayerMotion, &mut Velocity), With<PlayerSettings>>,
+) {
+    let step = Duration::from_secs_f32(time_scale.step());
+    for (mut dash, mut motion, mut velocity) in query.iter_mut() {
+        dash.cooldown.tick(step);
+        if dash.active.finished() {
+            continue;
+        }
+        // A hit cuts the dash short, the knockback owns the motion
+        if *motion == PlayerMotion::Hurt {
+            dash.active = finished_timer(DASH_SECS);
+            continue;
+        }
+        dash.active.tick(step);
+        if dash.active.finished() {
+            velocity.linear.x = 0.;
+            // `update_motion` picks the next motion
+            *motion = PlayerMotion::Grounded;
+        } else {
+            velocity.linear.x = dash.direction * DASH_SPEED;
+        }
+    }
+}
+
+fn despawn_effects(
+    mut commands: Commands,
+    animations: Res<Animations>,
+    query: Query<(Entity, &Animation), With<DashEffect>>,
+) {
+    for (entity, animation) in query.iter() {
+        if animation.finished(&animations) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
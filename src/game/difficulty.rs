@@ -0,0 +1,165 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use serde::Deserialize;
+
+use super::score::Score;
+
+const DIFFICULTY_FILE: &str = "game.difficulty.ron";
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Difficulty::default())
+            .add_startup_system(load_difficulty)
+            .add_system(update_difficulty);
+    }
+}
+
+/// Parameters reached at `distance` meters, see `assets/game.difficulty.ron`
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct DifficultyKeyframe {
+    pub distance: f32,
+    pub run_speed: f32,
+    pub min_gap: f32,
+    pub max_gap: f32,
+    pub obstacle_chance: f32,
+    pub max_enemies: u32,
+}
+
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "0d3b8a6e-7f41-4c2a-b5e9-3c6f1a2d4e80"]
+pub struct DifficultyAsset {
+    /// Sorted by distance, values are interpolated between two keyframes
+    pub keyframes: Vec<DifficultyKeyframe>,
+}
+
+pub struct DifficultyController {
+    handle: Handle<DifficultyAsset>,
+}
+
+impl DifficultyController {
+    /// Parameters at `distance` meters, the default until the curve is loaded
+    pub fn at(&self, assets: &Assets<DifficultyAsset>, distance: f32) -> Difficulty {
+        assets
+            .get(self.handle.clone())
+            .map_or_else(Difficulty::default, |curve| curve.at(distance))
+    }
+}
+
+/// Parameters of the current point of the run
+#[derive(Clone, Copy, Debug)]
+pub struct Difficulty {
+    /// Horizontal speed of the run, in pixels per second
+    pub run_speed: f32,
+    pub min_gap: f32,
+    pub max_gap: f32,
+    /// Probability for a platform to carry an obstacle
+    pub obstacle_chance: f32,
+    pub max_enemies: u32,
+}
+
+/// Used until the curve is loaded
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty {
+            run_speed: 180.0,
+            min_gap: 120.0,
+            max_gap: 260.0,
+            obstacle_chance: 0.0,
+            max_enemies: 0,
+        }
+    }
+}
+
+impl DifficultyAsset {
+    pub fn at(&self, distance: f32) -> Difficulty {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.distance > distance);
+        let (from, to) = match next {
+            None => match self.keyframes.last() {
+                Some(last) => (last, last),
+                None => return Difficulty::default(),
+            },
+            Some(0) => (&self.keyframes[0], &self.keyframes[0]),
+            Some(i) => (&self.keyframes[i - 1], &self.keyframes[i]),
+        };
+        let t = if to.distance > from.distance {
+            (distance - from.distance) / (to.distance - from.distance)
+        } else {
+            0.
+        };
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Difficulty {
+            run_speed: lerp(from.run_speed, to.run_speed),
+            min_gap: lerp(from.min_gap, to.min_gap),
+            max_gap: lerp(from.max_gap, to.max_gap),
+            obstacle_chance: lerp(from.obstacle_chance, to.obstacle_chance),
+            // Whole enemies are only added once a keyframe is reached
+            max_enemies: from.max_enemies,
+        }
+    }
+}
+
+fn load_difficulty(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load(DIFFICULTY_FILE);
+    commands.insert_resource(DifficultyController { handle });
+}
+
+/// Evaluated every frame so edits of the curve apply right away
+fn update_difficulty(
+    score: Res<Score>,
+    assets: Res<Assets<DifficultyAsset>>,
+    q: Res<DifficultyController>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    if let Some(curve) = assets.get(q.handle.clone()) {
+        *difficulty = curve.at(score.distance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(distance: f32, run_speed: f32, max_enemies: u32) -> DifficultyKeyframe {
+        DifficultyKeyframe {
+            distance,
+            run_speed,
+            min_gap: 100.,
+            max_gap: 200.,
+            obstacle_chance: run_speed / 1000.,
+            max_enemies,
+        }
+    }
+
+    fn curve() -> DifficultyAsset {
+        DifficultyAsset {
+            keyframes: vec![keyframe(0., 200., 0), keyframe(100., 300., 2)],
+        }
+    }
+
+    #[test]
+    fn interpolates_between_keyframes() {
+        let difficulty = curve().at(50.);
+        assert_eq!(difficulty.run_speed, 250.);
+        assert!((difficulty.obstacle_chance - 0.25).abs() < 1e-6);
+        // Enemies only step up at the next keyframe
+        assert_eq!(difficulty.max_enemies, 0);
+    }
+
+    #[test]
+    fn clamps_outside_the_curve() {
+        assert_eq!(curve().at(-10.).run_speed, 200.);
+        let end = curve().at(500.);
+        assert_eq!(end.run_speed, 300.);
+        assert_eq!(end.max_enemies, 2);
+    }
+
+    #[test]
+    fn empty_curve_uses_the_default() {
+        let empty = DifficultyAsset { keyframes: vec![] };
+        assert_eq!(empty.at(10.).run_speed, Difficulty::default().run_speed);
+    }
+}
@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+use heron::prelude::*;
+use rand::Rng;
+
+use super::{
+    config::ActiveConfig,
+    contacts::{dispatch_contacts, enemy_layers, EnemyContactEvent},
+    dash::PlayerDash,
+    platform::SegmentSpawned,
+    player::{PlayerHitEvent, PlayerSettings},
+    rng::{GameRng, RngStream},
+    timestep::{FixedUpdate, TimeScale},
+    view::{despawn_scrolled_out, ViewSize},
+    GameState, RunElement,
+};
+
+/// Chance for a new segment to bring an enemy, while under `max_enemies`
+const ENEMY_CHANCE: f64 = 0.5;
+/// Segments narrower than this only get flying enemies
+const MIN_PATROL_WIDTH: f32 = 144.0;
+const PATROLLER_SIZE: Vec2 = Vec2::new(40., 40.);
+const PATROLLER_COLOR: Color = Color::rgb(0.8, 0.2, 0.2);
+const PATROL_SPEED: f32 = 60.0;
+const FLYER_SIZE: Vec2 = Vec2::new(36., 28.);
+const FLYER_COLOR: Color = Color::rgb(0.6, 0.3, 0.8);
+const FLYER_HEIGHT: f32 = 180.0;
+const FLYER_AMPLITUDE: f32 = 40.0;
+const FLYER_FREQUENCY: f32 = 2.0;
+/// Vertical speed given to the player after a stomp
+const STOMP_BOUNCE_SPEED: f32 = 400.0;
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(spawn_enemies)
+                .with_system(enemy_contacts.after(dispatch_contacts))
+                .with_system(despawn_enemies),
+        )
+        .add_system_set_to_stage(
+            FixedUpdate,
+            SystemSet::on_update(GameState::InGame)
+                .with_system(patrol)
+                .with_system(fly),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct Enemy {
+    size: Vec2,
+}
+
+/// Walks back and forth on top of its segment
+#[derive(Component)]
+struct Patrol {
+    min_x: f32,
+    max_x: f32,
+    direction: f32,
+}
+
+/// Hovers up and down around `origin`
+#[derive(Component)]
+struct Flyer {
+    origin: Vec2,
+    elapsed: f32,
+}
+
+fn spawn_enemies(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    rng: Res<GameRng>,
+    mut segments: EventReader<SegmentSpawned>,
+    enemies: Query<(), With<Enemy>>,
+) {
+    let mut alive = enemies.iter().count() as u32;
+    for segment in segments.iter() {
+        let mut rng = rng.stream(RngStream::Enemies, segment.index);
+        if alive >= segment.difficulty.max_enemies || !rng.gen_bool(ENEMY_CHANCE) {
+            continue;
+        }
+        let top = segment.center.y + segment.size.y / 2.;
+        let flying = segment.size.x < MIN_PATROL_WIDTH || rng.gen_bool(0.5);
+        let (size, color, position, body) = if flying {
+            (
+                FLYER_SIZE,
+                FLYER_COLOR,
+                Vec2::new(segment.center.x, top + FLYER_HEIGHT),
+                RigidBody::KinematicPositionBased,
+            )
+        } else {
+            (
+                PATROLLER_SIZE,
+                PATROLLER_COLOR,
+                Vec2::new(segment.center.x, top + PATROLLER_SIZE.y / 2.),
+                RigidBody::KinematicVelocityBased,
+            )
+        };
+
+        let mut enemy = commands.spawn_bundle(SpriteBundle {
+            texture: asset_server.load("DebugPixel.png"),
+            transform: Transform::from_translation(position.extend(1.7)),
+            sprite: Sprite {
+                color,
+                custom_size: Some(size),
+                ..default()
+            },
+            ..default()
+        });
+        enemy
+            .insert(Enemy { size })
+            .insert(RunElement)
+            .insert(CollisionShape::Cuboid {
+                half_extends: (size / 2.).extend(0.),
+                border_radius: None,
+            })
+            .insert(enemy_layers())
+            .insert(Velocity::default())
+            .insert(body);
+        if flying {
+            enemy.insert(Flyer {
+                origin: position,
+                elapsed: rng.gen_range(0. ..std::f32::consts::TAU),
+            });
+        } else {
+            let reach = (segment.size.x - size.x) / 2.;
+            enemy.insert(Patrol {
+                min_x: segment.center.x - reach,
+                max_x: segment.center.x + reach,
+                direction: if rng.gen_bool(0.5) { 1. } else { -1. },
+            });
+        }
+        alive += 1;
+    }
+}
+
+fn patrol(mut query: Query<(&Transform, &mut Patrol, &mut Velocity)>) {
+    for (transform, mut patrol, mut velocity) in query.iter_mut() {
+        if transform.translation.x <= patrol.min_x {
+            patrol.direction = 1.;
+        } else if transform.translation.x >= patrol.max_x {
+            patrol.direction = -1.;
+        }
+        velocity.linear.x = patrol.direction * PATROL_SPEED;
+    }
+}
+
+fn fly(time_scale: Res<TimeScale>, mut query: Query<(&mut Transform, &mut Flyer)>) {
+    for (mut transform, mut flyer) in query.iter_mut() {
+        flyer.elapsed += time_scale.step();
+        transform.translation.y =
+            flyer.origin.y + FLYER_AMPLITUDE * (flyer.elapsed * FLYER_FREQUENCY).sin();
+    }
+}
+
+/// Landing on an enemy kills it, any other contact hurts the player
+fn enemy_contacts(
+    mut commands: Commands,
+    mut contacts: EventReader<EnemyContactEvent>,
+    mut hits: EventWriter<PlayerHitEvent>,
+    cfg: Res<ActiveConfig>,
+    mut player: Query<(&Transform, &PlayerDash, &mut Velocity), With<PlayerSettings>>,
+    enemies: Query<&Transform, (With<Enemy>, Without<PlayerSettings>)>,
+) {
+    let (transform, dash, mut velocity) = match player.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    for contact in contacts.iter() {
+        let enemy_transform = match enemies.get(contact.entity) {
+            Ok(enemy) => enemy,
+            Err(_) => continue,
+        };
+        // Dashing goes through enemies
+        if dash.invulnerable() {
+            continue;
+        }
+        let feet = transform.translation.y - cfg.player_box_size_y;
+        let stomp = velocity.linear.y <= 0. && feet >= enemy_transform.translation.y;
+        if stomp {
+            velocity.linear.y = STOMP_BOUNCE_SPEED;
+            commands.entity(contact.entity).despawn();
+        } else {
+            hits.send(PlayerHitEvent {
+                source: enemy_transform.translation.truncate(),
+            });
+        }
+    }
+}
+
+fn despawn_enemies(
+    mut commands: Commands,
+    view: Res<ViewSize>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    enemies: Query<(Entity, &Enemy, &Transform)>,
+) {
+    despawn_scrolled_out(
+        &mut commands,
+        &view,
+        &camera,
+        enemies.iter().map(|(entity, enemy, transform)| {
+            (entity, transform.translation.x + enemy.size.x / 2.)
+        }),
+    );
+}
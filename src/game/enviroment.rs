@@ -1,19 +1,34 @@
 #![allow(dead_code)]
 
-use bevy::prelude::*;
+use bevy::{core::FixedTimesteps, prelude::*};
 use bevy_asset_loader::AssetCollection;
 use bevy_parallax::{LayerData, ParallaxCameraComponent, ParallaxMoveEvent, ParallaxResource};
 use heron::prelude::*;
 
-use crate::{GameConfigAsset, GameConfigController};
-
-use super::{platform::draw_atlas, player::PLAYER_SPEED, GameState};
+use super::{
+    config::ActiveConfig,
+    difficulty::Difficulty,
+    player::PlayerSettings,
+    timestep::{overstep, FixedUpdate, TimeScale},
+    view::ViewSize,
+    GameState, RunElement,
+};
 
 const ENVIROMENT_WIDTH: f32 = 928.0;
 const ENVIROMENT_HEIGHT: f32 = 793.0;
 
 pub struct Enviroment;
 
+/// Horizontal scroll of the run, stepped at a fixed rate and
+/// interpolated for the frame being rendered
+#[derive(Default)]
+pub struct RunScroll {
+    previous: f32,
+    current: f32,
+    rendered: f32,
+}
+
+/// Not a body, falling below it through a gap ends the run
 #[derive(Component)]
 pub struct Floor;
 
@@ -33,6 +48,8 @@ pub enum Layer {
     Player,
     World,
     Enemy,
+    Pickup,
+    Hazard,
 }
 
 //
@@ -105,12 +122,15 @@ impl Plugin for Enviroment {
             ],
             ..Default::default()
         })
-        .add_system_set(
-            SystemSet::on_enter(GameState::MainMenu)
-                .with_system(setup_enviroment)
-                .with_system(draw_atlas),
+        .insert_resource(RunScroll::default())
+        .add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(setup_enviroment))
+        .add_system_set(SystemSet::on_enter(GameState::GameLoading).with_system(setup_enviroment))
+        .add_system_set_to_stage(
+            FixedUpdate,
+            SystemSet::on_update(GameState::InGame).with_system(follow_player),
         )
-        .add_system_set(SystemSet::on_update(GameState::InGame).with_system(move_camera_system));
+        .add_system_set(SystemSet::on_update(GameState::InGame).with_system(move_camera_system))
+        .add_system_set(SystemSet::on_exit(GameState::InGame).with_system(reset_parallax));
     }
 }
 
@@ -120,44 +140,87 @@ fn get_resource_name(name: &str) -> String {
 
 fn setup_enviroment(
     mut commands: Commands,
-    windows: Res<Windows>,
+    mut scroll: ResMut<RunScroll>,
+    view: Res<ViewSize>,
     asset_server: ResMut<AssetServer>,
-    assets: Res<Assets<GameConfigAsset>>,
-    q: Res<GameConfigController>,
+    cfg: Res<ActiveConfig>,
 ) {
-    let cfg = assets.get(q.handle.clone()).unwrap();
-    let window = windows.get_primary().unwrap();
+    *scroll = RunScroll::default();
     commands
         .spawn_bundle(OrthographicCameraBundle::new_2d())
-        .insert(ParallaxCameraComponent);
+        .insert(ParallaxCameraComponent)
+        .insert(RunElement);
 
     commands
         .spawn_bundle(SpriteBundle {
             texture: asset_server.load("DebugPixel.png"),
             transform: Transform {
-                translation: Vec3::new(0.0, -(window.height() * cfg.floor_multiplier), 5.0),
+                translation: Vec3::new(0.0, cfg.floor_y(view.height), 5.0),
                 ..Default::default()
             },
             ..default()
         })
         .insert(Floor)
-        .insert(CollisionShape::Cuboid {
-            half_extends: Vec2::new(window.width(), 50.0).extend(0.0),
-            border_radius: None,
-        })
-        .insert(RotationConstraints::lock())
-        .insert(RigidBody::Static);
+        .insert(RunElement);
 }
 
+/// Layers keep the offset of the last run, rebuild them from the start
+fn reset_parallax(
+    mut commands: Commands,
+    mut parallax: ResMut<ParallaxResource>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    parallax.despawn_layers(&mut commands);
+    parallax.create_layers(&mut commands, &asset_server, &mut texture_atlases);
+}
+
+/// Step the scroll towards the player, the floor follows it so the
+/// distance of the run doesn't depend on the frame rate
+fn follow_player(
+    mut scroll: ResMut<RunScroll>,
+    time_scale: Res<TimeScale>,
+    difficulty: Res<Difficulty>,
+    cfg: Res<ActiveConfig>,
+    player: Query<(&Transform, &Velocity), With<PlayerSettings>>,
+    mut floor: Query<&mut Transform, (With<Floor>, Without<PlayerSettings>)>,
+) {
+    let (player, velocity) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    scroll.previous = scroll.current;
+
+    // Look further ahead the faster the player runs
+    let look_ahead =
+        cfg.camera_look_ahead * (velocity.linear.x / difficulty.run_speed).clamp(-1., 1.);
+    let offset = player.translation.x + look_ahead - scroll.current;
+    if offset.abs() <= cfg.camera_dead_zone {
+        return;
+    }
+    let target = offset - cfg.camera_dead_zone * offset.signum();
+    let smoothing = 1. - (-cfg.camera_smoothing * time_scale.step()).exp();
+    // The run only goes forward, falling behind the camera loses it
+    scroll.current += (target * smoothing).max(0.);
+    for mut transform in floor.iter_mut() {
+        transform.translation.x = scroll.current;
+    }
+}
+
+/// Move the camera and the parallax layers to the scroll, interpolated
+/// between the last two steps
 pub fn move_camera_system(
+    timesteps: Res<FixedTimesteps>,
+    mut scroll: ResMut<RunScroll>,
     mut move_event_writer: EventWriter<ParallaxMoveEvent>,
-    mut query: Query<(&mut Floor, &mut Transform)>,
 ) {
+    let target = scroll.previous + (scroll.current - scroll.previous) * overstep(&timesteps);
+    let distance = target - scroll.rendered;
+    if distance == 0. {
+        return;
+    }
+    scroll.rendered = target;
     move_event_writer.send(ParallaxMoveEvent {
-        camera_move_speed: PLAYER_SPEED,
+        camera_move_speed: distance,
     });
-
-    for (_floor, mut transform) in query.iter_mut() {
-        transform.translation.x += PLAYER_SPEED;
-    }
 }
@@ -0,0 +1,250 @@
+use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+use leafwing_input_manager::prelude::*;
+
+use crate::GlobalUIAssets;
+
+use super::{
+    enviroment::Floor,
+    health::{take_damage, Health},
+    leaderboard::{InitialsText, Leaderboard},
+    mainmenu::build_btn,
+    player::{player_input, PlayerAction, PlayerSettings},
+    rng::GameRng,
+    score::Score,
+    view::ViewSize,
+    GameState,
+};
+
+const OVERLAY_COLOR: Color = Color::rgba(0., 0., 0., 0.75);
+/// How far the player can leave the camera before the run is lost
+const OUT_OF_BOUNDS_MARGIN: f32 = 100.0;
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(detect_death.after(take_damage).after(player_input)),
+        )
+        .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(setup_summary))
+        .add_system_set(
+            SystemSet::on_update(GameState::GameOver)
+                .with_system(button_system)
+                .with_system(highlight_buttons::<GameOverButton>),
+        )
+        .add_system_to_stage(CoreStage::PostUpdate, despawn_summary);
+    }
+}
+
+#[derive(Component)]
+struct GameOverSummary;
+
+#[derive(Component)]
+struct GameOverButton {
+    action: GameOverAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GameOverAction {
+    Retry,
+    MainMenu,
+}
+
+fn detect_death(
+    mut game_state: ResMut<State<GameState>>,
+    view: Res<ViewSize>,
+    player: Query<(&Transform, &Health, &ActionState<PlayerAction>), With<PlayerSettings>>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    floor: Query<&Transform, With<Floor>>,
+) {
+    let (transform, health, action) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    // The pause queued by `player_input` in the same frame wins,
+    // the death is caught again once the game resumes
+    if action.just_pressed(PlayerAction::Pause) {
+        return;
+    }
+
+    let out_of_bounds = match camera.get_single() {
+        Ok(camera) => {
+            let left_edge = camera.translation.x - view.width / 2.;
+            let bottom_edge = camera.translation.y - view.height / 2.;
+            transform.translation.x < left_edge - OUT_OF_BOUNDS_MARGIN
+                || transform.translation.y < bottom_edge - OUT_OF_BOUNDS_MARGIN
+        }
+        Err(_) => false,
+    };
+    let below_floor = match floor.get_single() {
+        Ok(floor) => transform.translation.y < floor.translation.y,
+        Err(_) => false,
+    };
+
+    // Falling off the track kills whatever health is left
+    if health.is_dead() || out_of_bounds || below_floor {
+        game_state.push(GameState::GameOver).unwrap();
+    }
+}
+
+fn button_system(
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<Button>)>,
+    btn_query: Query<&GameOverButton>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    for (interaction, children) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let btn = match button_label(children, &btn_query) {
+            Some(btn) => btn,
+            None => continue,
+        };
+        match btn.action {
+            GameOverAction::Retry => {
+                game_state.replace(GameState::GameLoading).unwrap();
+            }
+            GameOverAction::MainMenu => {
+                game_state.replace(GameState::MainMenu).unwrap();
+            }
+        }
+    }
+}
+
+fn setup_summary(
+    mut commands: Commands,
+    font_assets: Res<GlobalUIAssets>,
+    score: Res<Score>,
+    rng: Res<GameRng>,
+    leaderboard: Res<Leaderboard>,
+) {
+    let summary = vec![
+        format!("Distance: {:.0} m", score.distance),
+        format!("Score: {}", score.points),
+        format!("Time: {:.1} s", score.time),
+        format!("Seed: {}", rng.seed()),
+    ];
+    let text_buttons = vec![
+        ("Retry", GameOverAction::Retry),
+        ("Main Menu", GameOverAction::MainMenu),
+    ];
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: UiColor(OVERLAY_COLOR),
+            ..default()
+        })
+        .insert(GameOverSummary)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    margin: Rect {
+                        bottom: Val::Percent(4.),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "Game Over",
+                    TextStyle {
+                        font: font_assets.pixel_font.clone(),
+                        font_size: 62.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                ..default()
+            });
+            for line in summary {
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        line,
+                        TextStyle {
+                            font: font_assets.pixel_font.clone(),
+                            font_size: 32.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment {
+                            vertical: VerticalAlign::Center,
+                            horizontal: HorizontalAlign::Center,
+                        },
+                    ),
+                    ..default()
+                });
+            }
+            if leaderboard.qualifies(score.points) {
+                let style = TextStyle {
+                    font: font_assets.pixel_font.clone(),
+                    font_size: 32.0,
+                    color: Color::GOLD,
+                };
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text {
+                            sections: vec![
+                                TextSection {
+                                    value: "New record! Initials: ".to_string(),
+                                    style: style.clone(),
+                                },
+                                TextSection {
+                                    value: "___".to_string(),
+                                    style,
+                                },
+                            ],
+                            ..Default::default()
+                        },
+                        ..default()
+                    })
+                    .insert(InitialsText);
+            }
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        margin: Rect {
+                            top: Val::Percent(4.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    color: UiColor(Color::rgba(0., 0., 0., 0.)),
+                    ..default()
+                })
+                .with_children(|node_parent| {
+                    for (text, action) in text_buttons {
+                        build_btn(
+                            node_parent,
+                            font_assets.pixel_font.clone(),
+                            text,
+                            GameOverButton { action },
+                        );
+                    }
+                });
+        });
+}
+
+fn despawn_summary(
+    mut commands: Commands,
+    game_state: Res<State<GameState>>,
+    summary: Query<Entity, With<GameOverSummary>>,
+) {
+    if game_state.current() == &GameState::GameOver {
+        return;
+    }
+    for entity in summary.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::{
+    app::{AppExit, PluginGroupBuilder},
+    asset::AssetPlugin,
+    audio::AudioPlugin,
+    ecs::schedule::ShouldRun,
+    input::InputPlugin,
+    prelude::*,
+    render::texture::ImagePlugin,
+    text::TextPlugin,
+    transform::TransformPlugin,
+    window::WindowPlugin,
+};
+
+use super::{
+    arg_value, leaderboard::Leaderboard, rng::GameRng, score::Score, start_run,
+    timestep::FixedUpdate, GameState,
+};
+
+const HEADLESS_ARG: &str = "--headless";
+const FRAMES_ARG: &str = "--frames";
+/// Size of the simulated view, the window size of the settings is ignored
+pub const HEADLESS_VIEW_SIZE: (f32, f32) = (1280., 720.);
+
+/// What `MinimalPlugins` lacks to load the asset collections and run the
+/// gameplay, without any window, GPU or sound output
+pub struct HeadlessPlugins;
+
+impl PluginGroup for HeadlessPlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group.add(TransformPlugin::default());
+        group.add(InputPlugin::default());
+        group.add(WindowPlugin {
+            add_primary_window: false,
+            exit_on_close: false,
+        });
+        group.add(AssetPlugin::default());
+        group.add(ImagePlugin);
+        group.add(TextPlugin::default());
+        group.add(AudioPlugin::default());
+    }
+}
+
+/// Plays a single run without input and quits when it is lost, or after
+/// `--frames N` frames of play. Used with `--headless` to simulate runs
+/// where there is no display.
+/// Add it after the gameplay plugins, it replaces their timestep
+pub struct HeadlessPlugin;
+
+/// Frames of play left before quitting, unlimited without `--frames`
+struct FrameLimit(Option<u32>);
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        // Registered by the render plugins, still read by the gameplay ones
+        app.add_asset::<TextureAtlas>()
+            .add_asset::<ColorMaterial>()
+            // The summary of the game over reads it, the saved one is left alone
+            .insert_resource(Leaderboard::default())
+            .insert_resource(FrameLimit(arg_value(std::env::args().skip(1), FRAMES_ARG)))
+            // One step per frame and the frames back to back, a run is
+            // simulated as fast as the machine goes
+            .stage(FixedUpdate, |stage: &mut SystemStage| {
+                stage.set_run_criteria(every_frame)
+            })
+            .add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(start_run))
+            .add_system_set(SystemSet::on_update(GameState::InGame).with_system(limit_frames))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(report_run));
+    }
+}
+
+pub fn headless_from_args() -> bool {
+    std::env::args().skip(1).any(|arg| arg == HEADLESS_ARG)
+}
+
+fn every_frame() -> ShouldRun {
+    ShouldRun::Yes
+}
+
+fn limit_frames(
+    mut limit: ResMut<FrameLimit>,
+    score: Res<Score>,
+    rng: Res<GameRng>,
+    exit: EventWriter<AppExit>,
+) {
+    match &mut limit.0 {
+        Some(0) => report_run(score, rng, exit),
+        Some(frames) => *frames -= 1,
+        None => {}
+    }
+}
+
+fn report_run(score: Res<Score>, rng: Res<GameRng>, mut exit: EventWriter<AppExit>) {
+    info!(
+        "Run over with seed {}: {} points, {:.0} m in {:.1} s",
+        rng.seed(),
+        score.points,
+        score.distance,
+        score.time
+    );
+    exit.send(AppExit);
+}
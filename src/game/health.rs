@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_tweening::{lens::SpriteColorLens, Animator, EaseFunction, Tween, TweeningType};
+use heron::prelude::*;
+
+use super::{
+    config::ActiveConfig,
+    pickup::PowerUps,
+    player::{PlayerHitEvent, PlayerMotion, PlayerSettings},
+    timestep::{finished_timer, SteppedTime},
+    GameState,
+};
+
+/// Seconds the player can't steer after a hit
+const KNOCKBACK_SECS: f32 = 0.25;
+const BLINK_MILLIS: u64 = 80;
+const BLINK_COLOR: Color = Color::rgba(1., 1., 1., 0.2);
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::InGame)
+                .with_system(take_damage)
+                .with_system(tick_health.before(take_damage)),
+        );
+    }
+}
+
+/// Hit points of the player, with the timers started by the last hit
+#[derive(Component)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+    invulnerable: Timer,
+    knockback: Timer,
+}
+
+impl Health {
+    pub fn new(max: u32, invulnerability_time: f32) -> Self {
+        Health {
+            current: max,
+            max,
+            invulnerable: finished_timer(invulnerability_time),
+            knockback: finished_timer(KNOCKBACK_SECS),
+        }
+    }
+
+    pub fn kill(&mut self) {
+        self.current = 0;
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+
+    pub fn invulnerable(&self) -> bool {
+        !self.invulnerable.finished()
+    }
+}
+
+pub fn take_damage(
+    mut commands: Commands,
+    mut hits: EventReader<PlayerHitEvent>,
+    mut power_ups: ResMut<PowerUps>,
+    cfg: Res<ActiveConfig>,
+    mut player: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Health,
+            &mut PlayerMotion,
+            &mut Velocity,
+        ),
+        With<PlayerSettings>,
+    >,
+) {
+    let (entity, transform, mut health, mut motion, mut velocity) = match player.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    for hit in hits.iter() {
+        if health.invulnerable() || health.is_dead() {
+            continue;
+        }
+        health.invulnerable.reset();
+        // A shield takes the hit instead of the player
+        if !power_ups.absorb_hit() {
+            health.current -= 1;
+        }
+
+        let direction = if hit.source.x > transform.translation.x {
+            -1.
+        } else {
+            1.
+        };
+        velocity.linear.x = direction * cfg.knockback_speed;
+        velocity.linear.y = cfg.knockback_lift;
+        health.knockback.reset();
+        *motion = PlayerMotion::Hurt;
+
+        let blink = Tween::new(
+            EaseFunction::Linear,
+            TweeningType::PingPong,
+            Duration::from_millis(BLINK_MILLIS),
+            SpriteColorLens {
+                start: Color::WHITE,
+                end: BLINK_COLOR,
+            },
+        );
+        commands.entity(entity).insert(Animator::new(blink));
+    }
+}
+
+fn tick_health(
+    mut commands: Commands,
+    time: Res<SteppedTime>,
+    mut player: Query<(Entity, &mut Health, &mut PlayerMotion, &mut Sprite), With<PlayerSettings>>,
+) {
+    for (entity, mut health, mut motion, mut sprite) in player.iter_mut() {
+        if !health.knockback.finished() {
+            health.knockback.tick(time.delta());
+            if health.knockback.finished() && *motion == PlayerMotion::Hurt {
+                // `update_motion` picks the next motion
+                *motion = PlayerMotion::Grounded;
+            }
+        }
+        if health.invulnerable() {
+            health.invulnerable.tick(time.delta());
+            if !health.invulnerable() {
+                commands.entity(entity).remove::<Animator<Sprite>>();
+                sprite.color = Color::WHITE;
+            }
+        }
+    }
+}
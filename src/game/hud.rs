@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+
+use crate::GlobalUIAssets;
+
+use super::{health::Health, player::PlayerSettings, score::Score, GameState, RunElement};
+
+const HUD_FONT_SIZE: f32 = 28.0;
+const HEART_SIZE: f32 = 24.0;
+const HEART_COLOR: Color = Color::rgb(0.85, 0.1, 0.2);
+const EMPTY_HEART_COLOR: Color = Color::rgba(0.2, 0.2, 0.2, 0.6);
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::InGame).with_system(setup_hud))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(update_hud)
+                    .with_system(update_hearts),
+            );
+    }
+}
+
+#[derive(Component)]
+struct ScoreText;
+
+/// One heart per hit point, numbered from the left
+#[derive(Component)]
+struct Heart(u32);
+
+fn setup_hud(
+    mut commands: Commands,
+    font_assets: Res<GlobalUIAssets>,
+    player: Query<&Health, With<PlayerSettings>>,
+) {
+    let style = TextStyle {
+        font: font_assets.pixel_font.clone(),
+        font_size: HUD_FONT_SIZE,
+        color: Color::WHITE,
+    };
+    let section = |value: &str| TextSection {
+        value: value.to_string(),
+        style: style.clone(),
+    };
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(16.),
+                    left: Val::Px(24.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                sections: vec![
+                    section("Score: "),
+                    section("0"),
+                    section("\nDistance: "),
+                    section("0"),
+                    section(" m"),
+                    section(""),
+                ],
+                ..Default::default()
+            },
+            ..default()
+        })
+        .insert(ScoreText)
+        .insert(RunElement);
+
+    let max_health = player.get_single().map(|health| health.max).unwrap_or(0);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(16.),
+                    right: Val::Px(24.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(RunElement)
+        .with_children(|parent| {
+            for i in 0..max_health {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(HEART_SIZE), Val::Px(HEART_SIZE)),
+                            margin: Rect {
+                                left: Val::Px(6.),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        color: HEART_COLOR.into(),
+                        ..default()
+                    })
+                    .insert(Heart(i));
+            }
+        });
+}
+
+fn update_hud(score: Res<Score>, mut query: Query<&mut Text, With<ScoreText>>) {
+    if !score.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.sections[1].value = score.points.to_string();
+        text.sections[3].value = format!("{:.0}", score.distance);
+        text.sections[5].value = match score.multiplier() {
+            1 => String::new(),
+            multiplier => format!("\nCombo x{}", multiplier),
+        };
+    }
+}
+
+fn update_hearts(
+    player: Query<&Health, (With<PlayerSettings>, Changed<Health>)>,
+    mut hearts: Query<(&Heart, &mut UiColor)>,
+) {
+    let health = match player.get_single() {
+        Ok(health) => health,
+        Err(_) => return,
+    };
+    for (heart, mut color) in hearts.iter_mut() {
+        color.0 = if heart.0 < health.current {
+            HEART_COLOR
+        } else {
+            EMPTY_HEART_COLOR
+        };
+    }
+}
@@ -0,0 +1,207 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{rng::GameRng, score::Score, storage, GameState};
+
+const LEADERBOARD_FILE: &str = "leaderboard.ron";
+const MAX_RECORDS: usize = 10;
+const MAX_INITIALS: usize = 3;
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Leaderboard::load())
+            .insert_resource(Initials::default())
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(reset_initials))
+            .add_system_set(SystemSet::on_update(GameState::GameOver).with_system(type_initials))
+            .add_system_set(SystemSet::on_exit(GameState::GameOver).with_system(save_record));
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScoreRecord {
+    pub initials: String,
+    pub score: u32,
+    pub distance: f32,
+    pub seed: u64,
+    pub date: String,
+}
+
+/// Best runs, sorted from the highest score
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Leaderboard {
+    pub records: Vec<ScoreRecord>,
+}
+
+impl Leaderboard {
+    pub fn load() -> Self {
+        let mut leaderboard: Self = storage::load(LEADERBOARD_FILE).unwrap_or_default();
+        leaderboard.sort();
+        leaderboard
+    }
+
+    pub fn save(&self) {
+        storage::save(LEADERBOARD_FILE, self);
+    }
+
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.records.len() < MAX_RECORDS
+                || self.records.iter().any(|record| score > record.score))
+    }
+
+    pub fn insert(&mut self, record: ScoreRecord) {
+        self.records.push(record);
+        self.sort();
+    }
+
+    fn sort(&mut self) {
+        self.records.sort_by(|a, b| b.score.cmp(&a.score));
+        self.records.truncate(MAX_RECORDS);
+    }
+}
+
+/// Initials typed on the GameOver screen
+#[derive(Default)]
+pub struct Initials(pub String);
+
+#[derive(Component)]
+pub struct InitialsText;
+
+impl Initials {
+    pub fn display(&self) -> String {
+        format!("{:_<width$}", self.0, width = MAX_INITIALS)
+    }
+}
+
+fn reset_initials(mut initials: ResMut<Initials>) {
+    initials.0.clear();
+}
+
+fn type_initials(
+    mut initials: ResMut<Initials>,
+    mut chars: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut query: Query<&mut Text, With<InitialsText>>,
+) {
+    if keys.just_pressed(KeyCode::Back) {
+        initials.0.pop();
+    }
+    for event in chars.iter() {
+        if event.char.is_ascii_alphanumeric() && initials.0.len() < MAX_INITIALS {
+            initials.0.push(event.char.to_ascii_uppercase());
+        }
+    }
+    if initials.is_changed() {
+        for mut text in query.iter_mut() {
+            text.sections[1].value = initials.display();
+        }
+    }
+}
+
+fn save_record(
+    mut leaderboard: ResMut<Leaderboard>,
+    initials: Res<Initials>,
+    score: Res<Score>,
+    rng: Res<GameRng>,
+) {
+    if !leaderboard.qualifies(score.points) {
+        return;
+    }
+    leaderboard.insert(ScoreRecord {
+        initials: initials.display(),
+        score: score.points,
+        distance: score.distance,
+        seed: rng.seed(),
+        date: today(),
+    });
+    leaderboard.save();
+}
+
+/// Current UTC date as `YYYY-MM-DD`
+fn today() -> String {
+    date_from_unix(unix_now())
+}
+
+/// `YYYY-MM-DD` of a UTC unix timestamp in seconds
+fn date_from_unix(secs: u64) -> String {
+    // Days since epoch to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// `SystemTime` is not available in the browser
+#[cfg(target_arch = "wasm32")]
+fn unix_now() -> u64 {
+    (js_sys::Date::now() / 1000.) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(score: u32) -> ScoreRecord {
+        ScoreRecord {
+            initials: "AAA".to_string(),
+            score,
+            distance: 0.,
+            seed: 0,
+            date: "1970-01-01".to_string(),
+        }
+    }
+
+    #[test]
+    fn empty_board_takes_any_positive_score() {
+        let leaderboard = Leaderboard::default();
+        assert!(leaderboard.qualifies(1));
+        assert!(!leaderboard.qualifies(0));
+    }
+
+    #[test]
+    fn full_board_needs_to_beat_a_record() {
+        let mut leaderboard = Leaderboard::default();
+        for score in 1..=MAX_RECORDS as u32 {
+            leaderboard.insert(record(score * 10));
+        }
+        assert!(!leaderboard.qualifies(10));
+        assert!(leaderboard.qualifies(11));
+    }
+
+    #[test]
+    fn insert_keeps_the_best_records_sorted() {
+        let mut leaderboard = Leaderboard::default();
+        for score in 0..MAX_RECORDS as u32 + 5 {
+            leaderboard.insert(record(score));
+        }
+        let scores: Vec<u32> = leaderboard.records.iter().map(|r| r.score).collect();
+        let expected: Vec<u32> = (5..MAX_RECORDS as u32 + 5).rev().collect();
+        assert_eq!(scores, expected);
+    }
+
+    #[test]
+    fn dates_from_unix_time() {
+        assert_eq!(date_from_unix(0), "1970-01-01");
+        assert_eq!(date_from_unix(951_782_400), "2000-02-29");
+        assert_eq!(date_from_unix(1_700_000_000), "2023-11-14");
+    }
+}
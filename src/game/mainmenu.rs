@@ -1,34 +1,66 @@
 #![allow(clippy::type_complexity)]
 use std::time::Duration;
 
-use bevy::prelude::*;
-use bevy_tweening::{lens::TextColorLens, Animator, EaseFunction, Tween, TweeningType};
+use bevy::{prelude::*, reflect::TypeUuid};
+use bevy_tweening::{
+    lens::{TextColorLens, UiPositionLens},
+    Animator, EaseFunction, Tween, TweeningType,
+};
+use serde::Deserialize;
 
 use crate::GlobalUIAssets;
 
 use super::{
+    button_label, highlight_buttons,
+    leaderboard::Leaderboard,
     splash::UIElement,
     transition::{hide_text, show_text, TransitionElement},
-    GameState,
+    view::ViewSize,
+    ButtonPointer, GameState,
 };
 
 const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
 const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
+const CREDITS_ROLE_SIZE: f32 = 48.0;
+const CREDITS_NAME_SIZE: f32 = 32.0;
+const CREDITS_SECTION_GAP: f32 = 40.0;
+const CREDITS_SECS_PER_LINE: f32 = 1.5;
 
 pub struct MainMenu;
 
+/// Roles and names listed on the Credits screen, see `assets/game.credits.ron`
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "5e0c7f3a-2b8d-4c61-9f0e-8a4d1b7c3e92"]
+pub struct CreditsAsset {
+    pub sections: Vec<CreditsSection>,
+}
+
+#[derive(Deserialize)]
+pub struct CreditsSection {
+    pub role: String,
+    pub names: Vec<String>,
+}
+
+/// Root node of each screen of the menu
 #[derive(Component)]
-struct MainMenuButton {
-    action: MainMenuState,
+pub struct MenuScreen {
+    pub screen: MainMenuState,
+}
+
+#[derive(Component)]
+pub struct MainMenuButton {
+    pub action: MainMenuState,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum MainMenuState {
+pub enum MainMenuState {
     Main,
     Play,
     Options,
+    Controls,
     Credits,
+    Leaderboard,
     Quit,
 }
 
@@ -40,47 +72,80 @@ impl Plugin for MainMenu {
                     .with_system(setup_ui)
                     .with_system(show_text),
             )
-            .add_system_set(SystemSet::on_enter(MainMenuState::Main).with_system(show_text))
-            .add_system_set(SystemSet::on_update(GameState::MainMenu).with_system(button_system))
+            .add_system_set(
+                SystemSet::on_enter(MainMenuState::Main)
+                    .with_system(setup_ui)
+                    .with_system(show_text),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::MainMenu)
+                    .with_system(button_system)
+                    .with_system(highlight_buttons::<MainMenuButton>),
+            )
             .add_system_set(SystemSet::on_exit(GameState::MainMenu).with_system(hide_text))
             // Submenu Options
             .add_system_set(SystemSet::on_enter(MainMenuState::Options).with_system(show_text))
             .add_system_set(SystemSet::on_exit(MainMenuState::Options).with_system(hide_text))
+            // Submenu Controls, nested in Options
+            .add_system_set(SystemSet::on_enter(MainMenuState::Controls).with_system(show_text))
+            .add_system_set(SystemSet::on_exit(MainMenuState::Controls).with_system(hide_text))
             // Submenu Credits
-            .add_system_set(SystemSet::on_enter(MainMenuState::Credits).with_system(show_text))
-            .add_system_set(SystemSet::on_exit(MainMenuState::Credits).with_system(hide_text));
+            .add_system_set(
+                SystemSet::on_enter(MainMenuState::Credits)
+                    .with_system(setup_credits)
+                    .with_system(show_text),
+            )
+            .add_system_set(SystemSet::on_exit(MainMenuState::Credits).with_system(hide_text))
+            // Submenu Leaderboard
+            .add_system_set(
+                SystemSet::on_enter(MainMenuState::Leaderboard)
+                    .with_system(setup_leaderboard)
+                    .with_system(show_text),
+            )
+            .add_system_set(SystemSet::on_exit(MainMenuState::Leaderboard).with_system(hide_text))
+            // Screens are removed after the update stage so the transitions
+            // never touch entities that are being despawned
+            .add_system_to_stage(CoreStage::PostUpdate, despawn_screens);
     }
 }
 
 fn button_system(
-    mut interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<Button>)>,
-    mut text_query: Query<(&Text, &mut Visibility)>,
-    mut btn_query: Query<(&Text, &MainMenuButton)>,
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<Button>)>,
+    btn_query: Query<&MainMenuButton>,
     mut game_state: ResMut<State<GameState>>,
+    mut menu_state: ResMut<State<MainMenuState>>,
 ) {
-    for (interaction, children) in interaction_query.iter_mut() {
-        let (_text, mut visibility) = text_query.get_mut(children[0]).unwrap();
-        let (_text, btn) = btn_query.get_mut(children[1]).unwrap();
-        match *interaction {
-            Interaction::Clicked => {
-                match btn.action {
-                    MainMenuState::Play => {
-                        game_state.set(GameState::InGame).unwrap();
-                    }
-                    MainMenuState::Options => {}
-                    MainMenuState::Credits => {}
-                    MainMenuState::Quit => {
-                        std::process::exit(0);
-                    }
-                    // Return to main menu
-                    MainMenuState::Main => {}
-                }
+    for (interaction, children) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        // Buttons of other screens can still be alive during a transition
+        let btn = match button_label(children, &btn_query) {
+            Some(btn) => btn,
+            None => continue,
+        };
+        match btn.action {
+            MainMenuState::Play => {
+                game_state.set(GameState::InGame).unwrap();
+            }
+            MainMenuState::Options => {
+                menu_state.set(MainMenuState::Options).unwrap();
+            }
+            MainMenuState::Controls => {
+                menu_state.set(MainMenuState::Controls).unwrap();
+            }
+            MainMenuState::Credits => {
+                menu_state.set(MainMenuState::Credits).unwrap();
+            }
+            MainMenuState::Leaderboard => {
+                menu_state.set(MainMenuState::Leaderboard).unwrap();
             }
-            Interaction::Hovered => {
-                visibility.is_visible = true;
+            MainMenuState::Quit => {
+                std::process::exit(0);
             }
-            Interaction::None => {
-                visibility.is_visible = false;
+            // Return to main menu
+            MainMenuState::Main => {
+                menu_state.set(MainMenuState::Main).unwrap();
             }
         }
     }
@@ -88,14 +153,31 @@ fn button_system(
 
 fn setup_ui(
     mut commands: Commands,
-    font_assets: Res<GlobalUIAssets>,
+    game_state: Res<State<GameState>>,
+    font_assets: Option<Res<GlobalUIAssets>>,
     entity_text: Query<Entity, With<UIElement>>,
+    screens: Query<&MenuScreen>,
 ) {
+    // The Options can also be left back to the pause overlay
+    if game_state.current() != &GameState::MainMenu {
+        return;
+    }
+    // MainMenuState is entered on startup, before the assets are loaded
+    let font_assets = match font_assets {
+        Some(font_assets) => font_assets,
+        None => return,
+    };
     if let Ok(entity) = entity_text.get_single() {
         commands.entity(entity).despawn();
     }
+    // The menu is kept alive while playing, show_text brings it back
+    if screens
+        .iter()
+        .any(|menu| menu.screen == MainMenuState::Main)
+    {
+        return;
+    }
 
-    // build_credits_menu(&mut commands, &font_assets);
     build_main_menu(commands, font_assets);
 }
 
@@ -104,6 +186,7 @@ fn build_main_menu(mut commands: Commands, font_assets: Res<GlobalUIAssets>) {
         ("Play", MainMenuState::Play),
         ("Options", MainMenuState::Options),
         ("Credits", MainMenuState::Credits),
+        ("High Scores", MainMenuState::Leaderboard),
         ("Quit", MainMenuState::Quit),
     ];
     commands
@@ -122,6 +205,9 @@ fn build_main_menu(mut commands: Commands, font_assets: Res<GlobalUIAssets>) {
             color: UiColor(Color::rgba(0., 0., 0., 0.)),
             ..default()
         })
+        .insert(MenuScreen {
+            screen: MainMenuState::Main,
+        })
         .with_children(|parent| {
             parent
                 .spawn_bundle(TextBundle {
@@ -172,56 +258,72 @@ fn build_main_menu(mut commands: Commands, font_assets: Res<GlobalUIAssets>) {
                 })
                 .with_children(|node_parent| {
                     for btn in text_buttons {
-                        build_btn(node_parent, font_assets.pixel_font.clone(), btn.0, btn.1);
+                        build_btn(
+                            node_parent,
+                            font_assets.pixel_font.clone(),
+                            btn.0,
+                            MainMenuButton { action: btn.1 },
+                        );
                     }
                 });
         });
 }
 
-fn build_credits_menu(commands: &mut Commands, font_assets: &Res<GlobalUIAssets>) {
-    let credits = vec![
-        ("Programmer", vec!["Sergio Ribera"]),
-        ("Artist", vec!["Sergio Ribera"]),
-        ("Music", vec!["Sergio Ribera"]),
-        ("Sound Effects", vec!["Sergio Ribera"]),
-    ];
+fn setup_leaderboard(
+    mut commands: Commands,
+    font_assets: Res<GlobalUIAssets>,
+    leaderboard: Res<Leaderboard>,
+) {
+    let mut rows: Vec<String> = leaderboard
+        .records
+        .iter()
+        .enumerate()
+        .map(|(i, record)| {
+            format!(
+                "{:>2}. {}  {:>7}  {:>5.0} m  {}  #{}",
+                i + 1,
+                record.initials,
+                record.score,
+                record.distance,
+                record.date,
+                record.seed
+            )
+        })
+        .collect();
+    if rows.is_empty() {
+        rows.push("No scores yet".to_string());
+    }
+
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
                 flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
                 size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                position: Rect {
-                    top: Val::Percent(0.),
-                    left: Val::Percent(0.),
-                    right: Val::Auto,
-                    bottom: Val::Auto,
+                padding: Rect {
+                    top: Val::Percent(6.),
+                    ..Default::default()
                 },
                 ..Default::default()
             },
             color: UiColor(Color::rgba(0., 0., 0., 0.)),
             ..default()
         })
+        .insert(MenuScreen {
+            screen: MainMenuState::Leaderboard,
+        })
         .with_children(|parent| {
-            build_btn(
-                parent,
-                font_assets.pixel_font.clone(),
-                "Back",
-                MainMenuState::Main,
-            );
-
-            for (title, content) in credits {
-                parent.spawn_bundle(TextBundle {
+            parent
+                .spawn_bundle(TextBundle {
                     style: Style {
-                        size: Size::new(Val::Percent(100.0), Val::Percent(30.0)),
-                        position: Rect {
-                            bottom: Val::Percent(6.),
-                            left: Val::Percent(13.),
+                        margin: Rect {
+                            bottom: Val::Percent(3.),
                             ..Default::default()
                         },
                         ..Default::default()
                     },
                     text: Text::with_section(
-                        title,
+                        "High Scores",
                         TextStyle {
                             font: font_assets.pixel_font.clone(),
                             font_size: 62.0,
@@ -233,21 +335,17 @@ fn build_credits_menu(commands: &mut Commands, font_assets: &Res<GlobalUIAssets>
                         },
                     ),
                     ..default()
+                })
+                .insert(TransitionElement {
+                    color_target: Color::WHITE,
+                    ..default()
                 });
 
-                for text in content {
-                    parent.spawn_bundle(TextBundle {
-                        style: Style {
-                            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                            position: Rect {
-                                top: Val::Percent(0.),
-                                left: Val::Percent(0.),
-                                ..Default::default()
-                            },
-                            ..Default::default()
-                        },
+            for row in rows {
+                parent
+                    .spawn_bundle(TextBundle {
                         text: Text::with_section(
-                            text,
+                            row,
                             TextStyle {
                                 font: font_assets.pixel_font.clone(),
                                 font_size: 32.0,
@@ -259,13 +357,193 @@ fn build_credits_menu(commands: &mut Commands, font_assets: &Res<GlobalUIAssets>
                             },
                         ),
                         ..default()
+                    })
+                    .insert(TransitionElement {
+                        color_target: Color::WHITE,
+                        ..default()
                     });
-                }
             }
+
+            build_btn(
+                parent,
+                font_assets.pixel_font.clone(),
+                "Back",
+                MainMenuButton {
+                    action: MainMenuState::Main,
+                },
+            );
         });
 }
 
-fn build_btn(parent: &mut ChildBuilder, font: Handle<Font>, text: &str, action: MainMenuState) {
+fn despawn_screens(
+    mut commands: Commands,
+    menu_state: Res<State<MainMenuState>>,
+    screens: Query<(Entity, &MenuScreen)>,
+) {
+    for (entity, menu) in screens.iter() {
+        if &menu.screen != menu_state.current() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn setup_credits(
+    mut commands: Commands,
+    view: Res<ViewSize>,
+    font_assets: Res<GlobalUIAssets>,
+    credits: Res<Assets<CreditsAsset>>,
+) {
+    let credits = match credits.get(font_assets.credits.clone()) {
+        Some(credits) => credits,
+        None => return,
+    };
+    build_credits_menu(&mut commands, &font_assets, credits, view.height);
+}
+
+fn build_credits_menu(
+    commands: &mut Commands,
+    font_assets: &GlobalUIAssets,
+    credits: &CreditsAsset,
+    window_height: f32,
+) {
+    // Rough height of the whole list so it fully leaves the screen before looping
+    let names: usize = credits.sections.iter().map(|s| s.names.len()).sum();
+    let content_height = credits.sections.len() as f32 * (CREDITS_ROLE_SIZE + CREDITS_SECTION_GAP)
+        + names as f32 * CREDITS_NAME_SIZE;
+    let scroll = Tween::new(
+        EaseFunction::QuadraticInOut,
+        TweeningType::Loop,
+        Duration::from_secs_f32((credits.sections.len() + names) as f32 * CREDITS_SECS_PER_LINE),
+        UiPositionLens {
+            start: Rect {
+                top: Val::Px(window_height),
+                ..Default::default()
+            },
+            end: Rect {
+                top: Val::Px(-content_height),
+                ..Default::default()
+            },
+        },
+    );
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                overflow: Overflow::Hidden,
+                ..Default::default()
+            },
+            color: UiColor(Color::rgba(0., 0., 0., 0.)),
+            ..default()
+        })
+        .insert(MenuScreen {
+            screen: MainMenuState::Credits,
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        position_type: PositionType::Absolute,
+                        size: Size::new(Val::Percent(100.0), Val::Auto),
+                        position: Rect {
+                            top: Val::Px(window_height),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    color: UiColor(Color::rgba(0., 0., 0., 0.)),
+                    ..default()
+                })
+                .insert(Animator::new(scroll))
+                .with_children(|list| {
+                    for section in credits.sections.iter() {
+                        list.spawn_bundle(TextBundle {
+                            style: Style {
+                                margin: Rect {
+                                    top: Val::Px(CREDITS_SECTION_GAP),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                            text: Text::with_section(
+                                section.role.clone(),
+                                TextStyle {
+                                    font: font_assets.pixel_font.clone(),
+                                    font_size: CREDITS_ROLE_SIZE,
+                                    color: Color::GOLD,
+                                },
+                                TextAlignment {
+                                    vertical: VerticalAlign::Center,
+                                    horizontal: HorizontalAlign::Center,
+                                },
+                            ),
+                            ..default()
+                        })
+                        .insert(TransitionElement {
+                            color_target: Color::GOLD,
+                            ..default()
+                        });
+
+                        for name in section.names.iter() {
+                            list.spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    name.clone(),
+                                    TextStyle {
+                                        font: font_assets.pixel_font.clone(),
+                                        font_size: CREDITS_NAME_SIZE,
+                                        color: Color::WHITE,
+                                    },
+                                    TextAlignment {
+                                        vertical: VerticalAlign::Center,
+                                        horizontal: HorizontalAlign::Center,
+                                    },
+                                ),
+                                ..default()
+                            })
+                            .insert(TransitionElement {
+                                color_target: Color::WHITE,
+                                ..default()
+                            });
+                        }
+                    }
+                });
+
+            // The Back button stays in place while the list scrolls
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            bottom: Val::Percent(6.),
+                            right: Val::Percent(6.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    color: UiColor(Color::rgba(0., 0., 0., 0.)),
+                    ..default()
+                })
+                .with_children(|node_parent| {
+                    build_btn(
+                        node_parent,
+                        font_assets.pixel_font.clone(),
+                        "Back",
+                        MainMenuButton {
+                            action: MainMenuState::Main,
+                        },
+                    );
+                });
+        });
+}
+
+pub fn build_btn(
+    parent: &mut ChildBuilder,
+    font: Handle<Font>,
+    text: &str,
+    button: impl Component,
+) {
     parent
         .spawn_bundle(ButtonBundle {
             color: UiColor(Color::rgba(0., 0., 0., 0.)),
@@ -311,7 +589,8 @@ fn build_btn(parent: &mut ChildBuilder, font: Handle<Font>, text: &str, action:
                     color_target: Color::WHITE,
                     ..default()
                 })
-                .insert(Animator::new(tween));
+                .insert(Animator::new(tween))
+                .insert(ButtonPointer);
 
             // Instancing text content of button
             btn_parent
@@ -334,6 +613,6 @@ fn build_btn(parent: &mut ChildBuilder, font: Handle<Font>, text: &str, action:
                     color_target: Color::WHITE,
                     ..default()
                 })
-                .insert(MainMenuButton { action });
+                .insert(button);
         });
 }
@@ -1,53 +1,138 @@
 #![allow(dead_code)]
 
-use bevy::prelude::{Gamepad, GamepadButtonType, KeyCode};
+use std::str::FromStr;
+
+use bevy::{prelude::*, window::WindowMode};
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use self::player::PlayerAction;
 
+pub mod animation;
 pub mod audio;
+pub mod config;
+pub mod contacts;
+pub mod controls;
+pub mod dash;
+pub mod difficulty;
+pub mod enemy;
 pub mod enviroment;
+pub mod gameover;
+pub mod headless;
+pub mod health;
+pub mod hud;
+pub mod leaderboard;
 pub mod mainmenu;
+pub mod obstacle;
+pub mod options;
+pub mod pause;
+pub mod pickup;
 pub mod platform;
 pub mod player;
+pub mod rng;
+pub mod score;
+pub mod settings;
 pub mod splash;
+pub mod storage;
+pub mod timestep;
 pub mod transition;
+pub mod view;
+
+/// Marker for the entities that only live during a single run
+#[derive(Component)]
+pub struct RunElement;
 
+/// Input that can trigger a `PlayerAction`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Gamepad(button) => format!("Pad {:?}", button),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct GameSettings {
     pub music_volume: f32,
     pub vfx_volume: f32,
     pub fullscreen: bool,
-    pub player_ctrl: InputMap<PlayerAction>,
+    /// Window size when not in fullscreen
+    pub resolution: (f32, f32),
+    pub bindings: Vec<(PlayerAction, Binding)>,
 }
 
-impl Default for GameSettings {
-    fn default() -> Self {
+impl GameSettings {
+    pub fn player_ctrl(&self) -> InputMap<PlayerAction> {
         let mut ctrl = InputMap::default();
 
         ctrl.set_gamepad(Gamepad(0));
 
-        ctrl.insert(PlayerAction::Pause, KeyCode::Escape);
-        ctrl.insert(PlayerAction::Pause, GamepadButtonType::Select);
-
-        // Move to left
-        ctrl.insert(PlayerAction::MoveLeft, KeyCode::A);
-        ctrl.insert(PlayerAction::MoveLeft, KeyCode::Left);
-        ctrl.insert(PlayerAction::MoveLeft, GamepadButtonType::East);
-
-        // Move to right
-        ctrl.insert(PlayerAction::MoveRight, KeyCode::D);
-        ctrl.insert(PlayerAction::MoveRight, KeyCode::Right);
-        ctrl.insert(PlayerAction::MoveRight, GamepadButtonType::West);
+        for (action, binding) in self.bindings.iter() {
+            match *binding {
+                Binding::Key(key) => ctrl.insert(*action, key),
+                Binding::Gamepad(button) => ctrl.insert(*action, button),
+            };
+        }
+        ctrl
+    }
 
-        // Jump
-        ctrl.insert(PlayerAction::Jump, KeyCode::Space);
-        ctrl.insert(PlayerAction::Jump, GamepadButtonType::South);
+    pub fn window_mode(&self) -> WindowMode {
+        if self.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        }
+    }
+}
 
+impl Default for GameSettings {
+    fn default() -> Self {
         GameSettings {
             music_volume: 0.5,
             vfx_volume: 0.5,
             fullscreen: false,
-            player_ctrl: ctrl,
+            resolution: (1280., 720.),
+            bindings: vec![
+                (PlayerAction::Pause, Binding::Key(KeyCode::Escape)),
+                (
+                    PlayerAction::Pause,
+                    Binding::Gamepad(GamepadButtonType::Select),
+                ),
+                // Move to left
+                (PlayerAction::MoveLeft, Binding::Key(KeyCode::A)),
+                (PlayerAction::MoveLeft, Binding::Key(KeyCode::Left)),
+                (
+                    PlayerAction::MoveLeft,
+                    Binding::Gamepad(GamepadButtonType::East),
+                ),
+                // Move to right
+                (PlayerAction::MoveRight, Binding::Key(KeyCode::D)),
+                (PlayerAction::MoveRight, Binding::Key(KeyCode::Right)),
+                (
+                    PlayerAction::MoveRight,
+                    Binding::Gamepad(GamepadButtonType::West),
+                ),
+                // Jump
+                (PlayerAction::Jump, Binding::Key(KeyCode::Space)),
+                (
+                    PlayerAction::Jump,
+                    Binding::Gamepad(GamepadButtonType::South),
+                ),
+                // Dash
+                (PlayerAction::Dash, Binding::Key(KeyCode::LShift)),
+                (
+                    PlayerAction::Dash,
+                    Binding::Gamepad(GamepadButtonType::RightTrigger),
+                ),
+            ],
         }
     }
 }
@@ -62,3 +147,66 @@ pub enum GameState {
     Paused,
     GameOver,
 }
+
+/// Clean up everything spawned for the run that is being left
+pub fn despawn_run(mut commands: Commands, query: Query<Entity, With<RunElement>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// The run is rebuilt on enter `GameLoading`, start playing right after it.
+/// Headless, the run spawned by the main menu is started right away
+pub fn start_run(mut game_state: ResMut<State<GameState>>) {
+    game_state.set(GameState::InGame).unwrap();
+}
+
+/// Value of the first valid `name N` or `name=N` command line argument
+pub fn arg_value<T: FromStr>(mut args: impl Iterator<Item = String>, name: &str) -> Option<T> {
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix(name) {
+            Some("") => args.next(),
+            Some(value) => value.strip_prefix('=').map(str::to_string),
+            None => continue,
+        };
+        match value.as_deref().map(str::parse) {
+            Some(Ok(value)) => return Some(value),
+            _ => warn!("Ignoring invalid {} argument", name),
+        }
+    }
+    None
+}
+
+/// Pointer shown in front of a menu button while it is hovered, see `mainmenu::build_btn`
+#[derive(Component)]
+pub struct ButtonPointer;
+
+/// Label of type `T` among the children of a button built by `mainmenu::build_btn`
+pub fn button_label<'a, T: Component>(children: &Children, labels: &'a Query<&T>) -> Option<&'a T> {
+    children.iter().find_map(|child| labels.get(*child).ok())
+}
+
+/// Show the pointer of the hovered buttons labelled with a `T`,
+/// each screen registers it for the buttons it handles
+pub fn highlight_buttons<T: Component>(
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<Button>)>,
+    labels: Query<&T>,
+    mut pointers: Query<&mut Visibility, With<ButtonPointer>>,
+) {
+    for (interaction, children) in interaction_query.iter() {
+        // Buttons of other screens can still be alive during a transition
+        if button_label(children, &labels).is_none() {
+            continue;
+        }
+        let is_visible = match *interaction {
+            Interaction::Clicked => continue,
+            Interaction::Hovered => true,
+            Interaction::None => false,
+        };
+        for child in children.iter() {
+            if let Ok(mut visibility) = pointers.get_mut(*child) {
+                visibility.is_visible = is_visible;
+            }
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use bevy_parallax::ParallaxCameraComponent;
+use heron::prelude::*;
+use rand::{distributions::WeightedIndex, Rng};
+use serde::Deserialize;
+
+use super::{
+    contacts::{dispatch_contacts, hazard_layers, HazardContactEvent},
+    enviroment::EnviromentAssets,
+    health::Health,
+    platform::SegmentSpawned,
+    player::{PlayerHitEvent, PlayerSettings},
+    rng::{GameRng, RngStream},
+    timestep::{FixedUpdate, TimeScale},
+    view::{despawn_scrolled_out, ViewSize},
+    GameState, RunElement,
+};
+
+const OBSTACLES_FILE: &str = "game.obstacles.ron";
+
+pub struct ObstaclePlugin;
+
+impl Plugin for ObstaclePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_obstacles)
+            .add_system(check_obstacles.before(spawn_obstacles))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(spawn_obstacles)
+                    .with_system(hazard_contacts.after(dispatch_contacts))
+                    .with_system(despawn_obstacles),
+            )
+            .add_system_set_to_stage(
+                FixedUpdate,
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(drop_obstacles)
+                    .with_system(oscillate_obstacles),
+            );
+    }
+}
+
+/// How an obstacle moves once placed
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum ObstacleBehavior {
+    Static,
+    /// Hangs over the platform and drops when the player gets close
+    Falling {
+        height: f32,
+        trigger_distance: f32,
+        gravity: f32,
+    },
+    /// Moves back and forth by `offset` around its place
+    Oscillating {
+        offset: (f32, f32),
+        frequency: f32,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ObstacleKind {
+    pub name: String,
+    /// Index in `EnviromentAssets::platforms`
+    pub tile: usize,
+    pub size: (f32, f32),
+    /// Relative chance against the other obstacles
+    pub weight: u32,
+    pub behavior: ObstacleBehavior,
+    /// Ends the run on contact instead of taking one hit point
+    #[serde(default)]
+    pub lethal: bool,
+}
+
+/// Catalog of the obstacles, see `assets/game.obstacles.ron`
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "a7c4e2b9-1d38-4f6a-9b05-6e2d8c3f7a14"]
+pub struct ObstacleAsset {
+    pub obstacles: Vec<ObstacleKind>,
+}
+
+impl ObstacleAsset {
+    /// Every kind that can't be placed, as a readable message
+    pub fn validate(&self, tiles: usize) -> Vec<String> {
+        let mut errors = Vec::new();
+        for kind in self.obstacles.iter() {
+            if kind.tile >= tiles {
+                errors.push(format!(
+                    "{}: tile {} is out of the {} tiles of the sheet",
+                    kind.name, kind.tile, tiles
+                ));
+            }
+            if kind.size.0.is_nan()
+                || kind.size.0 <= 0.
+                || kind.size.1.is_nan()
+                || kind.size.1 <= 0.
+            {
+                errors.push(format!(
+                    "{}: size must be positive, got {:?}",
+                    kind.name, kind.size
+                ));
+            }
+        }
+        errors
+    }
+}
+
+pub struct ObstacleController {
+    handle: Handle<ObstacleAsset>,
+    /// Result of `ObstacleAsset::validate` for the loaded catalog,
+    /// nothing is placed until it is checked and valid
+    valid: Option<bool>,
+}
+
+#[derive(Component)]
+pub struct Obstacle {
+    size: Vec2,
+    lethal: bool,
+}
+
+#[derive(Component)]
+struct Falling {
+    trigger_distance: f32,
+    gravity: f32,
+    /// Height where it lands on its platform
+    rest_y: f32,
+    speed: f32,
+    triggered: bool,
+}
+
+#[derive(Component)]
+struct Oscillating {
+    origin: Vec2,
+    offset: Vec2,
+    frequency: f32,
+    elapsed: f32,
+}
+
+fn load_obstacles(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load(OBSTACLES_FILE);
+    commands.insert_resource(ObstacleController {
+        handle,
+        valid: None,
+    });
+}
+
+/// Check the catalog against the platforms sheet once both are loaded,
+/// and again every time the file changes
+fn check_obstacles(
+    mut events: EventReader<AssetEvent<ObstacleAsset>>,
+    enviroment: Option<Res<EnviromentAssets>>,
+    atlases: Res<Assets<TextureAtlas>>,
+    assets: Res<Assets<ObstacleAsset>>,
+    mut q: ResMut<ObstacleController>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if *handle == q.handle {
+                q.valid = None;
+            }
+        }
+    }
+    if q.valid.is_some() {
+        return;
+    }
+    let atlas = match enviroment.and_then(|enviroment| atlases.get(enviroment.platforms.clone())) {
+        Some(atlas) => atlas,
+        None => return,
+    };
+    let catalog = match assets.get(q.handle.clone()) {
+        Some(catalog) => catalog,
+        None => return,
+    };
+    let errors = catalog.validate(atlas.len());
+    for error in errors.iter() {
+        error!("{}: {}", OBSTACLES_FILE, error);
+    }
+    q.valid = Some(errors.is_empty());
+}
+
+fn spawn_obstacles(
+    mut commands: Commands,
+    enviroment: Res<EnviromentAssets>,
+    assets: Res<Assets<ObstacleAsset>>,
+    q: Res<ObstacleController>,
+    rng: Res<GameRng>,
+    mut segments: EventReader<SegmentSpawned>,
+) {
+    if q.valid != Some(true) {
+        return;
+    }
+    let catalog = match assets.get(q.handle.clone()) {
+        Some(catalog) => catalog,
+        None => return,
+    };
+    let weights = match WeightedIndex::new(catalog.obstacles.iter().map(|kind| kind.weight)) {
+        Ok(weights) => weights,
+        Err(_) => return,
+    };
+
+    for segment in segments.iter() {
+        let mut rng = rng.stream(RngStream::Obstacles, segment.index);
+        let chance = segment.difficulty.obstacle_chance.clamp(0., 1.) as f64;
+        if !rng.gen_bool(chance) {
+            continue;
+        }
+        let kind = &catalog.obstacles[rng.sample(&weights)];
+        let size = Vec2::new(kind.size.0, kind.size.1);
+        let reach = (segment.size.x - size.x) / 2.;
+        if reach < 0. {
+            continue;
+        }
+        let top = segment.center.y + segment.size.y / 2.;
+        let rest = Vec2::new(
+            segment.center.x + rng.gen_range(-reach..=reach),
+            top + size.y / 2.,
+        );
+        let position = match kind.behavior {
+            ObstacleBehavior::Falling { height, .. } => rest + Vec2::new(0., height),
+            // Never sink into the platform
+            ObstacleBehavior::Oscillating { offset, .. } => rest + Vec2::new(0., offset.1.abs()),
+            ObstacleBehavior::Static => rest,
+        };
+
+        let mut obstacle = commands.spawn_bundle(SpriteSheetBundle {
+            texture_atlas: enviroment.platforms.clone(),
+            transform: Transform::from_translation(position.extend(1.65)),
+            sprite: TextureAtlasSprite {
+                index: kind.tile,
+                custom_size: Some(size),
+                ..default()
+            },
+            ..default()
+        });
+        obstacle
+            .insert(Name::new(kind.name.clone()))
+            .insert(Obstacle {
+                size,
+                lethal: kind.lethal,
+            })
+            .insert(RunElement)
+            .insert(CollisionShape::Cuboid {
+                half_extends: (size / 2.).extend(0.),
+                border_radius: None,
+            })
+            .insert(hazard_layers())
+            .insert(RigidBody::Sensor);
+        match kind.behavior {
+            ObstacleBehavior::Static => {}
+            ObstacleBehavior::Falling {
+                trigger_distance,
+                gravity,
+                ..
+            } => {
+                obstacle.insert(Falling {
+                    trigger_distance,
+                    gravity,
+                    rest_y: rest.y,
+                    speed: 0.,
+                    triggered: false,
+                });
+            }
+            ObstacleBehavior::Oscillating { offset, frequency } => {
+                obstacle.insert(Oscillating {
+                    origin: position,
+                    offset: Vec2::new(offset.0, offset.1),
+                    frequency,
+                    elapsed: 0.,
+                });
+            }
+        }
+    }
+}
+
+fn drop_obstacles(
+    time_scale: Res<TimeScale>,
+    player: Query<&Transform, With<PlayerSettings>>,
+    mut query: Query<(&mut Transform, &mut Falling), Without<PlayerSettings>>,
+) {
+    let player = match player.get_single() {
+        Ok(player) => player.translation.x,
+        Err(_) => return,
+    };
+    let step = time_scale.step();
+    for (mut transform, mut falling) in query.iter_mut() {
+        if !falling.triggered {
+            falling.triggered = transform.translation.x - player <= falling.trigger_distance;
+            continue;
+        }
+        if transform.translation.y <= falling.rest_y {
+            continue;
+        }
+        falling.speed += falling.gravity * step;
+        transform.translation.y =
+            (transform.translation.y - falling.speed * step).max(falling.rest_y);
+    }
+}
+
+fn oscillate_obstacles(
+    time_scale: Res<TimeScale>,
+    mut query: Query<(&mut Transform, &mut Oscillating)>,
+) {
+    for (mut transform, mut oscillating) in query.iter_mut() {
+        oscillating.elapsed += time_scale.step();
+        let phase = (oscillating.elapsed * oscillating.frequency * std::f32::consts::TAU).sin();
+        let position = oscillating.origin + oscillating.offset * phase;
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+/// Every hazard hurts the player, dashing doesn't help and lethal ones
+/// kill whatever health or shield is left
+fn hazard_contacts(
+    mut contacts: EventReader<HazardContactEvent>,
+    mut hits: EventWriter<PlayerHitEvent>,
+    obstacles: Query<(&Obstacle, &Transform)>,
+    mut player: Query<&mut Health, With<PlayerSettings>>,
+) {
+    for contact in contacts.iter() {
+        let (obstacle, transform) = match obstacles.get(contact.entity) {
+            Ok(obstacle) => obstacle,
+            Err(_) => continue,
+        };
+        if obstacle.lethal {
+            for mut health in player.iter_mut() {
+                health.kill();
+            }
+        } else {
+            hits.send(PlayerHitEvent {
+                source: transform.translation.truncate(),
+            });
+        }
+    }
+}
+
+fn despawn_obstacles(
+    mut commands: Commands,
+    view: Res<ViewSize>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    obstacles: Query<(Entity, &Obstacle, &Transform)>,
+) {
+    despawn_scrolled_out(
+        &mut commands,
+        &view,
+        &camera,
+        obstacles.iter().map(|(entity, obstacle, transform)| {
+            (entity, transform.translation.x + obstacle.size.x / 2.)
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(tile: usize, size: (f32, f32)) -> ObstacleKind {
+        ObstacleKind {
+            name: "test".to_string(),
+            tile,
+            size,
+            weight: 1,
+            behavior: ObstacleBehavior::Static,
+            lethal: false,
+        }
+    }
+
+    #[test]
+    fn tiles_out_of_the_sheet_are_reported() {
+        let catalog = ObstacleAsset {
+            obstacles: vec![kind(11, (16., 16.)), kind(12, (16., 16.))],
+        };
+        let errors = catalog.validate(12);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("tile 12"));
+    }
+
+    #[test]
+    fn empty_sizes_are_reported() {
+        let catalog = ObstacleAsset {
+            obstacles: vec![kind(0, (0., 16.)), kind(0, (16., f32::NAN))],
+        };
+        assert_eq!(catalog.validate(12).len(), 2);
+    }
+}
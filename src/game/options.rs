@@ -0,0 +1,310 @@
+#![allow(clippy::type_complexity)]
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use crate::GlobalUIAssets;
+
+use super::{
+    button_label, highlight_buttons,
+    mainmenu::{build_btn, MainMenuButton, MainMenuState, MenuScreen},
+    transition::TransitionElement,
+    GameSettings,
+};
+
+const SLIDER_WIDTH: f32 = 300.0;
+const SLIDER_HEIGHT: f32 = 16.0;
+const SLIDER_TRACK: Color = Color::rgba(1., 1., 1., 0.2);
+const SLIDER_FILL: Color = Color::WHITE;
+/// Window sizes offered while not in fullscreen
+const RESOLUTIONS: [(f32, f32); 4] = [(1280., 720.), (1366., 768.), (1600., 900.), (1920., 1080.)];
+
+pub struct OptionsPlugin;
+
+impl Plugin for OptionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(MainMenuState::Options).with_system(setup_options))
+            .add_system_set(
+                SystemSet::on_update(MainMenuState::Options)
+                    .with_system(button_system)
+                    .with_system(highlight_buttons::<OptionButton>)
+                    .with_system(slider_system)
+                    .with_system(update_options),
+            );
+    }
+}
+
+#[derive(Component)]
+struct OptionButton {
+    action: OptionAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OptionAction {
+    Fullscreen,
+    Resolution,
+}
+
+impl OptionAction {
+    fn label(self, settings: &GameSettings) -> String {
+        match self {
+            OptionAction::Fullscreen => format!(
+                "Fullscreen: {}",
+                if settings.fullscreen { "On" } else { "Off" }
+            ),
+            OptionAction::Resolution => format!(
+                "Resolution: {}x{}",
+                settings.resolution.0, settings.resolution.1
+            ),
+        }
+    }
+}
+
+/// Volume that is edited by a slider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Volume {
+    Music,
+    Vfx,
+}
+
+impl Volume {
+    fn get(self, settings: &GameSettings) -> f32 {
+        match self {
+            Volume::Music => settings.music_volume,
+            Volume::Vfx => settings.vfx_volume,
+        }
+    }
+
+    fn set(self, settings: &mut GameSettings, value: f32) {
+        match self {
+            Volume::Music => settings.music_volume = value,
+            Volume::Vfx => settings.vfx_volume = value,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Slider {
+    volume: Volume,
+}
+
+#[derive(Component)]
+struct SliderFill {
+    volume: Volume,
+}
+
+fn button_system(
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<Button>)>,
+    btn_query: Query<&OptionButton>,
+    mut settings: ResMut<GameSettings>,
+) {
+    for (interaction, children) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let btn = match button_label(children, &btn_query) {
+            Some(btn) => btn,
+            None => continue,
+        };
+        match btn.action {
+            OptionAction::Fullscreen => {
+                settings.fullscreen = !settings.fullscreen;
+            }
+            OptionAction::Resolution => {
+                let next = RESOLUTIONS
+                    .iter()
+                    .position(|resolution| *resolution == settings.resolution)
+                    .map_or(0, |i| (i + 1) % RESOLUTIONS.len());
+                settings.resolution = RESOLUTIONS[next];
+            }
+        }
+    }
+}
+
+/// Drag the sliders while the mouse button is held
+fn slider_system(
+    windows: Res<Windows>,
+    sliders: Query<(&Interaction, &Node, &GlobalTransform, &Slider)>,
+    mut settings: ResMut<GameSettings>,
+) {
+    let cursor = match windows.get_primary().and_then(|w| w.cursor_position()) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    for (interaction, node, transform, slider) in sliders.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let left = transform.translation.x - node.size.x / 2.;
+        let value = ((cursor.x - left) / node.size.x).clamp(0., 1.);
+        // Only touch the settings on a real change
+        if (slider.volume.get(&settings) - value).abs() > f32::EPSILON {
+            slider.volume.set(&mut settings, value);
+        }
+    }
+}
+
+fn update_options(
+    settings: Res<GameSettings>,
+    mut fills: Query<(&mut Style, &SliderFill)>,
+    mut labels: Query<(&mut Text, &OptionButton)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (mut style, fill) in fills.iter_mut() {
+        style.size.width = Val::Percent(fill.volume.get(&settings) * 100.);
+    }
+    for (mut text, btn) in labels.iter_mut() {
+        text.sections[0].value = btn.action.label(&settings);
+    }
+}
+
+fn setup_options(
+    mut commands: Commands,
+    font_assets: Res<GlobalUIAssets>,
+    settings: Res<GameSettings>,
+) {
+    let sliders = vec![("Music", Volume::Music), ("Effects", Volume::Vfx)];
+    let text_buttons = vec![OptionAction::Fullscreen, OptionAction::Resolution];
+    let text_style = TextStyle {
+        font: font_assets.pixel_font.clone(),
+        font_size: 32.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: Rect {
+                    top: Val::Percent(6.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            color: UiColor(Color::rgba(0., 0., 0., 0.)),
+            ..default()
+        })
+        .insert(MenuScreen {
+            screen: MainMenuState::Options,
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect {
+                            bottom: Val::Percent(3.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        "Options",
+                        TextStyle {
+                            font: font_assets.pixel_font.clone(),
+                            font_size: 62.0,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment {
+                            vertical: VerticalAlign::Center,
+                            horizontal: HorizontalAlign::Center,
+                        },
+                    ),
+                    ..default()
+                })
+                .insert(TransitionElement {
+                    color_target: Color::WHITE,
+                    ..default()
+                });
+
+            for (label, volume) in sliders {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            margin: Rect::all(Val::Px(8.)),
+                            ..Default::default()
+                        },
+                        color: UiColor(Color::rgba(0., 0., 0., 0.)),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn_bundle(TextBundle {
+                            style: Style {
+                                margin: Rect {
+                                    right: Val::Px(24.),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                            text: Text::with_section(
+                                label,
+                                text_style.clone(),
+                                TextAlignment::default(),
+                            ),
+                            ..default()
+                        })
+                        .insert(TransitionElement {
+                            color_target: Color::WHITE,
+                            ..default()
+                        });
+                        // Not a `Button`, the sliders are dragged by `slider_system` instead
+                        row.spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(SLIDER_WIDTH), Val::Px(SLIDER_HEIGHT)),
+                                ..Default::default()
+                            },
+                            color: UiColor(SLIDER_TRACK),
+                            ..default()
+                        })
+                        .insert(Interaction::default())
+                        .insert(FocusPolicy::Block)
+                        .insert(Slider { volume })
+                        .with_children(|track| {
+                            track
+                                .spawn_bundle(NodeBundle {
+                                    style: Style {
+                                        size: Size::new(
+                                            Val::Percent(volume.get(&settings) * 100.),
+                                            Val::Percent(100.),
+                                        ),
+                                        ..Default::default()
+                                    },
+                                    color: UiColor(SLIDER_FILL),
+                                    focus_policy: FocusPolicy::Pass,
+                                    ..default()
+                                })
+                                .insert(SliderFill { volume });
+                        });
+                    });
+            }
+
+            for action in text_buttons {
+                build_btn(
+                    parent,
+                    font_assets.pixel_font.clone(),
+                    &action.label(&settings),
+                    OptionButton { action },
+                );
+            }
+
+            build_btn(
+                parent,
+                font_assets.pixel_font.clone(),
+                "Controls",
+                MainMenuButton {
+                    action: MainMenuState::Controls,
+                },
+            );
+
+            build_btn(
+                parent,
+                font_assets.pixel_font.clone(),
+                "Back",
+                MainMenuButton {
+                    action: MainMenuState::Main,
+                },
+            );
+        });
+}
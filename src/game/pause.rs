@@ -0,0 +1,201 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::GlobalUIAssets;
+
+use super::{
+    button_label, highlight_buttons,
+    mainmenu::{build_btn, MainMenuButton, MainMenuState},
+    player::PlayerAction,
+    GameState,
+};
+
+const OVERLAY_COLOR: Color = Color::rgba(0., 0., 0., 0.6);
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::Paused).with_system(setup_overlay))
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused)
+                    .with_system(submenu_system)
+                    .with_system(highlight_buttons::<PauseButton>)
+                    .with_system(highlight_buttons::<MainMenuButton>),
+            )
+            // Back from the Options opened from the overlay
+            .add_system_set(SystemSet::on_enter(MainMenuState::Main).with_system(reopen_overlay))
+            // Runs once per frame, so the pause action that opened the
+            // overlay is not read again after entering the pause
+            .add_system(button_system)
+            // Removed after the update stage so transitions out of the pause
+            // never touch the overlay entities while they are being despawned
+            .add_system_to_stage(CoreStage::PostUpdate, despawn_overlay);
+    }
+}
+
+#[derive(Component)]
+struct PauseOverlay;
+
+#[derive(Component)]
+struct PauseButton {
+    action: PauseAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PauseAction {
+    Resume,
+    Restart,
+    Options,
+    Quit,
+}
+
+fn button_system(
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<Button>)>,
+    btn_query: Query<&PauseButton>,
+    input_query: Query<&ActionState<PlayerAction>>,
+    mut game_state: ResMut<State<GameState>>,
+    mut menu_state: ResMut<State<MainMenuState>>,
+) {
+    // The Options opened from the overlay have to be closed first
+    if game_state.current() != &GameState::Paused || menu_state.current() != &MainMenuState::Main {
+        return;
+    }
+    let mut clicked = None;
+    for (interaction, children) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        // Skip the buttons of the hidden main menu
+        if let Some(btn) = button_label(children, &btn_query) {
+            clicked = Some(btn.action.clone());
+        }
+    }
+    // The pause action works like the Resume button
+    if input_query
+        .iter()
+        .any(|action| action.just_pressed(PlayerAction::Pause))
+    {
+        clicked.get_or_insert(PauseAction::Resume);
+    }
+    if let Some(action) = clicked {
+        match action {
+            PauseAction::Resume => {
+                game_state.pop().unwrap();
+            }
+            PauseAction::Restart => {
+                game_state.replace(GameState::GameLoading).unwrap();
+            }
+            PauseAction::Options => {
+                menu_state.set(MainMenuState::Options).unwrap();
+            }
+            PauseAction::Quit => {
+                game_state.replace(GameState::MainMenu).unwrap();
+            }
+        }
+    }
+}
+
+/// Move between the Options screens opened from the overlay,
+/// their buttons are only handled by the main menu outside of a run
+fn submenu_system(
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<Button>)>,
+    btn_query: Query<&MainMenuButton>,
+    mut menu_state: ResMut<State<MainMenuState>>,
+) {
+    // The main menu kept alive during the run also has such buttons
+    if menu_state.current() == &MainMenuState::Main {
+        return;
+    }
+    for (interaction, children) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        if let Some(btn) = button_label(children, &btn_query) {
+            menu_state.set(btn.action.clone()).unwrap();
+        }
+    }
+}
+
+fn reopen_overlay(
+    commands: Commands,
+    game_state: Res<State<GameState>>,
+    font_assets: Option<Res<GlobalUIAssets>>,
+) {
+    if game_state.current() != &GameState::Paused {
+        return;
+    }
+    if let Some(font_assets) = font_assets {
+        setup_overlay(commands, font_assets);
+    }
+}
+
+fn setup_overlay(mut commands: Commands, font_assets: Res<GlobalUIAssets>) {
+    let text_buttons = vec![
+        ("Resume", PauseAction::Resume),
+        ("Restart", PauseAction::Restart),
+        ("Options", PauseAction::Options),
+        ("Quit to Menu", PauseAction::Quit),
+    ];
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: UiColor(OVERLAY_COLOR),
+            ..default()
+        })
+        .insert(PauseOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    margin: Rect {
+                        bottom: Val::Percent(4.),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "Paused",
+                    TextStyle {
+                        font: font_assets.pixel_font.clone(),
+                        font_size: 62.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                ..default()
+            });
+            for (text, action) in text_buttons {
+                build_btn(
+                    parent,
+                    font_assets.pixel_font.clone(),
+                    text,
+                    PauseButton { action },
+                );
+            }
+        });
+}
+
+fn despawn_overlay(
+    mut commands: Commands,
+    game_state: Res<State<GameState>>,
+    menu_state: Res<State<MainMenuState>>,
+    overlay: Query<Entity, With<PauseOverlay>>,
+) {
+    // Also hidden behind the Options opened from it
+    if game_state.current() == &GameState::Paused && menu_state.current() == &MainMenuState::Main {
+        return;
+    }
+    for entity in overlay.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
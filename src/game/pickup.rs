@@ -0,0 +1,345 @@
+use bevy::{prelude::*, reflect::TypeUuid, utils::HashMap};
+use bevy_parallax::ParallaxCameraComponent;
+use heron::prelude::*;
+use rand::{distributions::WeightedIndex, Rng};
+use serde::Deserialize;
+
+use super::{
+    contacts::{dispatch_contacts, pickup_layers, PickupContactEvent},
+    platform::SegmentSpawned,
+    player::PlayerSettings,
+    rng::{GameRng, RngStream},
+    score::PickupEvent,
+    timestep::{FixedUpdate, SteppedTime, TimeScale},
+    view::{despawn_scrolled_out, ViewSize},
+    GameState, RunElement,
+};
+
+const PICKUPS_FILE: &str = "game.pickups.ron";
+const COIN_SIZE: Vec2 = Vec2::new(20., 20.);
+const COIN_COLOR: Color = Color::rgb(1.0, 0.84, 0.0);
+const POWER_UP_SIZE: Vec2 = Vec2::new(32., 32.);
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PowerUps::default())
+            .add_startup_system(load_pickups)
+            .add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(reset_power_ups))
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameLoading).with_system(reset_power_ups),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(spawn_pickups)
+                    .with_system(collect_pickups.after(dispatch_contacts))
+                    .with_system(tick_power_ups)
+                    .with_system(despawn_pickups),
+            )
+            .add_system_set_to_stage(
+                FixedUpdate,
+                SystemSet::on_update(GameState::InGame).with_system(attract_coins),
+            );
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUpKind {
+    /// Pulls nearby coins towards the player
+    Magnet,
+    /// Absorbs the next hit
+    Shield,
+    /// Multiplies the points of every pickup
+    Multiplier,
+    /// Slows down the gameplay and the physics
+    SlowMotion,
+}
+
+impl PowerUpKind {
+    fn color(&self) -> Color {
+        match self {
+            PowerUpKind::Magnet => Color::rgb(0.9, 0.2, 0.3),
+            PowerUpKind::Shield => Color::rgb(0.2, 0.6, 1.0),
+            PowerUpKind::Multiplier => Color::rgb(0.3, 0.9, 0.3),
+            PowerUpKind::SlowMotion => Color::rgb(0.7, 0.5, 1.0),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct CoinRules {
+    pub chance: f64,
+    pub min_count: u32,
+    pub max_count: u32,
+    pub spacing: f32,
+    pub hover: f32,
+    pub arc_height: f32,
+    pub points: u32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct PowerUpRule {
+    pub kind: PowerUpKind,
+    /// Relative chance against the other power-ups
+    pub weight: u32,
+    /// Seconds the effect lasts
+    pub duration: f32,
+}
+
+/// Spawn rules and effects of the pickups, see `assets/game.pickups.ron`
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "5b1f7d2c-9e64-4a0b-8c3d-2f6e1b7a9d41"]
+pub struct PickupAsset {
+    pub coins: CoinRules,
+    pub power_up_chance: f64,
+    pub power_up_hover: f32,
+    pub power_up_points: u32,
+    pub power_ups: Vec<PowerUpRule>,
+    pub magnet_radius: f32,
+    pub magnet_speed: f32,
+    pub score_multiplier: u32,
+    pub slow_motion_scale: f32,
+}
+
+pub struct PickupController {
+    handle: Handle<PickupAsset>,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pickup {
+    Coin,
+    PowerUp(PowerUpKind),
+}
+
+impl Pickup {
+    fn size(&self) -> Vec2 {
+        match self {
+            Pickup::Coin => COIN_SIZE,
+            Pickup::PowerUp(_) => POWER_UP_SIZE,
+        }
+    }
+}
+
+/// Seconds left of every active power-up
+#[derive(Default)]
+pub struct PowerUps(HashMap<PowerUpKind, f32>);
+
+impl PowerUps {
+    pub fn active(&self, kind: PowerUpKind) -> bool {
+        self.0.contains_key(&kind)
+    }
+
+    pub fn remaining(&self, kind: PowerUpKind) -> Option<f32> {
+        self.0.get(&kind).copied()
+    }
+
+    /// Uses up the shield, returns whether there was one
+    pub fn absorb_hit(&mut self) -> bool {
+        self.0.remove(&PowerUpKind::Shield).is_some()
+    }
+}
+
+fn load_pickups(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load(PICKUPS_FILE);
+    commands.insert_resource(PickupController { handle });
+}
+
+fn reset_power_ups(mut power_ups: ResMut<PowerUps>, mut time_scale: ResMut<TimeScale>) {
+    power_ups.0.clear();
+    *time_scale = TimeScale::default();
+}
+
+fn spawn_pickup(commands: &mut Commands, texture: Handle<Image>, pickup: Pickup, position: Vec2) {
+    let size = pickup.size();
+    let color = match pickup {
+        Pickup::Coin => COIN_COLOR,
+        Pickup::PowerUp(kind) => kind.color(),
+    };
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture,
+            transform: Transform::from_translation(position.extend(1.65)),
+            sprite: Sprite {
+                color,
+                custom_size: Some(size),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(pickup)
+        .insert(RunElement)
+        .insert(CollisionShape::Cuboid {
+            half_extends: (size / 2.).extend(0.),
+            border_radius: None,
+        })
+        .insert(pickup_layers())
+        .insert(RigidBody::Sensor);
+}
+
+/// Each segment gets either a power-up or an arc of coins over it
+fn spawn_pickups(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    assets: Res<Assets<PickupAsset>>,
+    q: Res<PickupController>,
+    rng: Res<GameRng>,
+    mut segments: EventReader<SegmentSpawned>,
+) {
+    let rules = match assets.get(q.handle.clone()) {
+        Some(rules) => rules,
+        None => return,
+    };
+    let weights = WeightedIndex::new(rules.power_ups.iter().map(|rule| rule.weight)).ok();
+    // The asset is edited by hand and reloaded live
+    let power_up_chance = rules.power_up_chance.clamp(0., 1.);
+    let coin_chance = rules.coins.chance.clamp(0., 1.);
+    let texture: Handle<Image> = asset_server.load("DebugPixel.png");
+
+    for segment in segments.iter() {
+        let mut rng = rng.stream(RngStream::Pickups, segment.index);
+        let top = segment.center.y + segment.size.y / 2.;
+        if let Some(weights) = &weights {
+            if rng.gen_bool(power_up_chance) {
+                let kind = rules.power_ups[rng.sample(weights)].kind;
+                let position = Vec2::new(segment.center.x, top + rules.power_up_hover);
+                spawn_pickup(
+                    &mut commands,
+                    texture.clone(),
+                    Pickup::PowerUp(kind),
+                    position,
+                );
+                continue;
+            }
+        }
+
+        let coins = &rules.coins;
+        if coins.max_count == 0 || !rng.gen_bool(coin_chance) {
+            continue;
+        }
+        let count = rng.gen_range(coins.min_count.min(coins.max_count)..=coins.max_count);
+        let width = (count - 1) as f32 * coins.spacing;
+        for i in 0..count {
+            let t = if count > 1 {
+                i as f32 / (count - 1) as f32
+            } else {
+                0.5
+            };
+            let position = Vec2::new(
+                segment.center.x - width / 2. + i as f32 * coins.spacing,
+                top + coins.hover + coins.arc_height * (t * std::f32::consts::PI).sin(),
+            );
+            spawn_pickup(&mut commands, texture.clone(), Pickup::Coin, position);
+        }
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    mut contacts: EventReader<PickupContactEvent>,
+    mut events: EventWriter<PickupEvent>,
+    assets: Res<Assets<PickupAsset>>,
+    q: Res<PickupController>,
+    mut power_ups: ResMut<PowerUps>,
+    mut time_scale: ResMut<TimeScale>,
+    pickups: Query<&Pickup>,
+) {
+    let rules = match assets.get(q.handle.clone()) {
+        Some(rules) => rules,
+        None => return,
+    };
+    for contact in contacts.iter() {
+        let pickup = match pickups.get(contact.entity) {
+            Ok(pickup) => *pickup,
+            Err(_) => continue,
+        };
+        commands.entity(contact.entity).despawn();
+
+        let points = match pickup {
+            Pickup::Coin => rules.coins.points,
+            Pickup::PowerUp(kind) => {
+                if let Some(rule) = rules.power_ups.iter().find(|rule| rule.kind == kind) {
+                    power_ups.0.insert(kind, rule.duration);
+                }
+                if kind == PowerUpKind::SlowMotion {
+                    time_scale.0 = rules.slow_motion_scale;
+                }
+                rules.power_up_points
+            }
+        };
+        let multiplier = if power_ups.active(PowerUpKind::Multiplier) {
+            rules.score_multiplier
+        } else {
+            1
+        };
+        events.send(PickupEvent {
+            points: points * multiplier,
+        });
+    }
+}
+
+fn tick_power_ups(
+    time: Res<SteppedTime>,
+    mut power_ups: ResMut<PowerUps>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    let delta = time.delta_seconds();
+    let slowed = power_ups.active(PowerUpKind::SlowMotion);
+    power_ups.0.retain(|_, remaining| {
+        *remaining -= delta;
+        *remaining > 0.
+    });
+    if slowed && !power_ups.active(PowerUpKind::SlowMotion) {
+        *time_scale = TimeScale::default();
+    }
+}
+
+fn attract_coins(
+    assets: Res<Assets<PickupAsset>>,
+    q: Res<PickupController>,
+    power_ups: Res<PowerUps>,
+    time_scale: Res<TimeScale>,
+    player: Query<&Transform, With<PlayerSettings>>,
+    mut coins: Query<(&Pickup, &mut Transform), Without<PlayerSettings>>,
+) {
+    if !power_ups.active(PowerUpKind::Magnet) {
+        return;
+    }
+    let rules = match assets.get(q.handle.clone()) {
+        Some(rules) => rules,
+        None => return,
+    };
+    let player = match player.get_single() {
+        Ok(player) => player.translation.truncate(),
+        Err(_) => return,
+    };
+    let step = rules.magnet_speed * time_scale.step();
+    for (pickup, mut transform) in coins.iter_mut() {
+        if *pickup != Pickup::Coin {
+            continue;
+        }
+        let offset = player - transform.translation.truncate();
+        let distance = offset.length();
+        if distance > rules.magnet_radius || distance <= f32::EPSILON {
+            continue;
+        }
+        let movement = offset / distance * step.min(distance);
+        transform.translation += movement.extend(0.);
+    }
+}
+
+fn despawn_pickups(
+    mut commands: Commands,
+    view: Res<ViewSize>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    pickups: Query<(Entity, &Pickup, &Transform)>,
+) {
+    despawn_scrolled_out(
+        &mut commands,
+        &view,
+        &camera,
+        pickups.iter().map(|(entity, pickup, transform)| {
+            (entity, transform.translation.x + pickup.size().x / 2.)
+        }),
+    );
+}
@@ -1,24 +1,379 @@
 use bevy::core::Timer;
 use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+use heron::prelude::*;
+use rand::Rng;
 
-use super::enviroment::EnviromentAssets;
+use super::{
+    config::ActiveConfig,
+    contacts::world_layers,
+    difficulty::{Difficulty, DifficultyAsset, DifficultyController},
+    enviroment::EnviromentAssets,
+    player::Ground,
+    rng::{GameRng, RngStream},
+    score::PIXELS_PER_METER,
+    timestep::SteppedTime,
+    view::{despawn_scrolled_out, ViewSize},
+    GameState, RunElement,
+};
+
+const TILE_SIZE: f32 = 16.0;
+const TILE_SCALE: f32 = 3.0;
+const ATLAS_COLUMNS: usize = 12;
+
+// Tiles of the platforms sheet used to compose a segment
+const TILE_TOP_LEFT: usize = 0;
+const TILE_TOP_MIDDLE: usize = 1;
+const TILE_TOP_RIGHT: usize = 2;
+const TILE_FILL_LEFT: usize = ATLAS_COLUMNS;
+const TILE_FILL_MIDDLE: usize = ATLAS_COLUMNS + 1;
+const TILE_FILL_RIGHT: usize = ATLAS_COLUMNS + 2;
+
+const SPAWN_INTERVAL_SECS: f32 = 0.25;
+const MIN_SEGMENT_COLUMNS: usize = 3;
+const MAX_SEGMENT_COLUMNS: usize = 8;
+const MAX_SEGMENT_ROWS: usize = 2;
+const MAX_STEP_Y: f32 = 120.0;
+/// Heights the segments stay between, fixed so the track doesn't depend on the window
+const MIN_SEGMENT_Y: f32 = -180.0;
+const MAX_SEGMENT_Y: f32 = 36.0;
+/// Chance of a pit in a segment, relative to `Difficulty::obstacle_chance`
+const PIT_SHARE: f32 = 0.5;
+const MAX_PIT_COLUMNS: usize = 2;
+/// Columns kept on each side of a pit
+const MIN_PIT_EDGE_COLUMNS: usize = 2;
+
+pub struct PlatformPlugin;
+
+impl Plugin for PlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PlatformData::default())
+            .add_event::<SegmentSpawned>()
+            .add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(setup_platforms))
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameLoading).with_system(setup_platforms),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(generate_platforms)
+                    .with_system(despawn_platforms),
+            );
+    }
+}
 
 pub struct PlatformData {
     pub interval: Timer,
+    /// Center of the first segment, where the track starts
+    pub start_x: f32,
+    /// Left edge where the next segment can be placed
+    pub next_x: f32,
+    /// Height of the last spawned segment
+    pub last_y: f32,
+    /// Number of the next `SegmentSpawned`
+    pub next_index: u64,
+}
+
+impl Default for PlatformData {
+    fn default() -> Self {
+        Self {
+            interval: Timer::from_seconds(SPAWN_INTERVAL_SECS, true),
+            start_x: 0.,
+            next_x: 0.,
+            last_y: 0.,
+            next_index: 0,
+        }
+    }
+}
+
+/// Sent for every generated segment, so other content can be placed on it
+pub struct SegmentSpawned {
+    /// Position in the run, picks the random streams of the segment
+    pub index: u64,
+    pub center: Vec2,
+    pub size: Vec2,
+    /// Parameters at the distance of the segment
+    pub difficulty: Difficulty,
+}
+
+#[derive(Component)]
+pub struct Platform {
+    pub width: f32,
+}
+
+fn setup_platforms(
+    mut commands: Commands,
+    view: Res<ViewSize>,
+    assets: Res<EnviromentAssets>,
+    cfg: Res<ActiveConfig>,
+    mut data: ResMut<PlatformData>,
+) {
+    let start_x = cfg.player_start_x(view.width);
+    // The player spawns at y = 0, its feet rest on the top of the first segment
+    let y = -cfg.player_box_size_y - TILE_SIZE * TILE_SCALE / 2.;
+    let width = draw_atlas(
+        &mut commands,
+        assets.platforms.clone(),
+        Vec2::new(start_x, y),
+        MIN_SEGMENT_COLUMNS,
+        1,
+    );
+
+    *data = PlatformData {
+        start_x,
+        next_x: start_x + width / 2.,
+        last_y: y,
+        ..default()
+    };
+}
+
+fn generate_platforms(
+    mut commands: Commands,
+    time: Res<SteppedTime>,
+    view: Res<ViewSize>,
+    assets: Res<EnviromentAssets>,
+    mut data: ResMut<PlatformData>,
+    rng: Res<GameRng>,
+    curves: Res<Assets<DifficultyAsset>>,
+    curve: Res<DifficultyController>,
+    mut segments: EventWriter<SegmentSpawned>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+) {
+    if !data.interval.tick(time.delta()).just_finished() {
+        return;
+    }
+    let camera = match camera.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let spawn_limit = camera.translation.x + view.width;
+
+    // Fill everything up to one screen ahead of the camera
+    while data.next_x < spawn_limit {
+        // Read at the place of the segment rather than where the player is
+        let difficulty = curve.at(&curves, (data.next_x - data.start_x) / PIXELS_PER_METER);
+        let mut stream = rng.stream(RngStream::Platforms, data.next_index);
+        let layout = next_segment(&mut stream, &difficulty, data.last_y);
+        let tile = TILE_SIZE * TILE_SCALE;
+        let left = data.next_x + layout.gap;
+
+        // A pit splits the segment in two, each side is a segment of its own
+        for (first, columns) in layout.parts() {
+            let center = Vec2::new(left + (first as f32 + columns as f32 / 2.) * tile, layout.y);
+            let width = draw_atlas(
+                &mut commands,
+                assets.platforms.clone(),
+                center,
+                columns,
+                layout.rows,
+            );
+            segments.send(SegmentSpawned {
+                index: data.next_index,
+                center,
+                size: Vec2::new(width, layout.rows as f32 * tile),
+                difficulty,
+            });
+            data.next_index += 1;
+        }
+        data.next_x = left + layout.columns as f32 * tile;
+        data.last_y = layout.y;
+    }
 }
 
-pub fn draw_atlas(mut commands: Commands, windows: Res<Windows>, assets: Res<EnviromentAssets>) {
-    let window = windows.get_primary().unwrap();
-    let intit_player_pos_x = -(window.width() * 0.35);
-    let atlas = assets.platforms.clone();
-    // draw single texture from sprite sheet starting at index 0
-    commands.spawn_bundle(SpriteSheetBundle {
-        transform: Transform {
-            translation: Vec3::new(intit_player_pos_x, 0., 1.6),
-            ..Default::default()
-        },
-        sprite: TextureAtlasSprite::new(0),
-        texture_atlas: atlas,
-        ..Default::default()
-    });
+/// Shape and place of a segment, relative to the previous one
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SegmentLayout {
+    columns: usize,
+    rows: usize,
+    gap: f32,
+    y: f32,
+    /// First column and width of the columns left out for a pit
+    pit: Option<(usize, usize)>,
+}
+
+impl SegmentLayout {
+    /// First column and width of the runs of columns that are drawn
+    fn parts(&self) -> Vec<(usize, usize)> {
+        match self.pit {
+            Some((first, width)) => vec![(0, first), (first + width, self.columns - first - width)],
+            None => vec![(0, self.columns)],
+        }
+    }
+}
+
+/// Only draws from `rng`, so the same stream always gives the same segment
+fn next_segment(rng: &mut impl Rng, difficulty: &Difficulty, last_y: f32) -> SegmentLayout {
+    let columns = rng.gen_range(MIN_SEGMENT_COLUMNS..=MAX_SEGMENT_COLUMNS);
+    let rows = rng.gen_range(1..=MAX_SEGMENT_ROWS);
+    let gap = rng.gen_range(difficulty.min_gap..difficulty.max_gap.max(difficulty.min_gap + 1.));
+    let y = (last_y + rng.gen_range(-MAX_STEP_Y..MAX_STEP_Y)).clamp(MIN_SEGMENT_Y, MAX_SEGMENT_Y);
+    let pit_chance = (difficulty.obstacle_chance * PIT_SHARE).clamp(0., 1.) as f64;
+    let pit = if columns >= 2 * MIN_PIT_EDGE_COLUMNS + MAX_PIT_COLUMNS && rng.gen_bool(pit_chance) {
+        let width = rng.gen_range(1..=MAX_PIT_COLUMNS);
+        let first = rng.gen_range(MIN_PIT_EDGE_COLUMNS..=columns - width - MIN_PIT_EDGE_COLUMNS);
+        Some((first, width))
+    } else {
+        None
+    };
+    SegmentLayout {
+        columns,
+        rows,
+        gap,
+        y,
+        pit,
+    }
+}
+
+fn despawn_platforms(
+    mut commands: Commands,
+    view: Res<ViewSize>,
+    camera: Query<&Transform, With<ParallaxCameraComponent>>,
+    platforms: Query<(Entity, &Platform, &Transform)>,
+) {
+    despawn_scrolled_out(
+        &mut commands,
+        &view,
+        &camera,
+        platforms.iter().map(|(entity, platform, transform)| {
+            (entity, transform.translation.x + platform.width / 2.)
+        }),
+    );
+}
+
+/// Spawns a static platform segment of `columns` x `rows` tiles centered on `position`
+/// and returns its width in world units
+pub fn draw_atlas(
+    commands: &mut Commands,
+    atlas: Handle<TextureAtlas>,
+    position: Vec2,
+    columns: usize,
+    rows: usize,
+) -> f32 {
+    let tile = TILE_SIZE * TILE_SCALE;
+    let size = Vec2::new(columns as f32 * tile, rows as f32 * tile);
+
+    commands
+        .spawn_bundle(TransformBundle::from_transform(
+            Transform::from_translation(position.extend(1.6)),
+        ))
+        .insert(Platform { width: size.x })
+        .insert(Ground)
+        .insert(RunElement)
+        .insert(CollisionShape::Cuboid {
+            half_extends: (size / 2.).extend(0.),
+            border_radius: None,
+        })
+        .insert(world_layers())
+        .insert(RigidBody::Static)
+        .with_children(|parent| {
+            for row in 0..rows {
+                for column in 0..columns {
+                    parent.spawn_bundle(SpriteSheetBundle {
+                        transform: Transform {
+                            translation: Vec3::new(
+                                (column as f32 + 0.5) * tile - size.x / 2.,
+                                size.y / 2. - (row as f32 + 0.5) * tile,
+                                0.,
+                            ),
+                            scale: Vec3::splat(TILE_SCALE),
+                            ..Default::default()
+                        },
+                        sprite: TextureAtlasSprite::new(tile_index(row, column, columns)),
+                        texture_atlas: atlas.clone(),
+                        ..Default::default()
+                    });
+                }
+            }
+        });
+
+    size.x
+}
+
+fn tile_index(row: usize, column: usize, columns: usize) -> usize {
+    let top = row == 0;
+    if column == 0 {
+        if top {
+            TILE_TOP_LEFT
+        } else {
+            TILE_FILL_LEFT
+        }
+    } else if column == columns - 1 {
+        if top {
+            TILE_TOP_RIGHT
+        } else {
+            TILE_FILL_RIGHT
+        }
+    } else if top {
+        TILE_TOP_MIDDLE
+    } else {
+        TILE_FILL_MIDDLE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(seed: u64) -> Vec<SegmentLayout> {
+        let rng = GameRng::from_seed(seed);
+        let difficulty = Difficulty::default();
+        let mut last_y = 0.;
+        (0..32)
+            .map(|index| {
+                let mut stream = rng.stream(RngStream::Platforms, index);
+                let segment = next_segment(&mut stream, &difficulty, last_y);
+                last_y = segment.y;
+                segment
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_segments() {
+        assert_eq!(track(42), track(42));
+        assert_ne!(track(42), track(43));
+    }
+
+    #[test]
+    fn segments_stay_in_bounds() {
+        let difficulty = Difficulty::default();
+        for segment in track(7) {
+            assert!((MIN_SEGMENT_COLUMNS..=MAX_SEGMENT_COLUMNS).contains(&segment.columns));
+            assert!((1..=MAX_SEGMENT_ROWS).contains(&segment.rows));
+            assert!(segment.gap >= difficulty.min_gap && segment.gap < difficulty.max_gap);
+            assert!((MIN_SEGMENT_Y..=MAX_SEGMENT_Y).contains(&segment.y));
+        }
+    }
+
+    #[test]
+    fn pits_keep_both_edges() {
+        let difficulty = Difficulty {
+            obstacle_chance: 1. / PIT_SHARE,
+            ..Difficulty::default()
+        };
+        let rng = GameRng::from_seed(3);
+        let mut pits = 0;
+        for index in 0..64 {
+            let mut stream = rng.stream(RngStream::Platforms, index);
+            let segment = next_segment(&mut stream, &difficulty, 0.);
+            let parts = segment.parts();
+            if segment.pit.is_some() {
+                pits += 1;
+                assert_eq!(parts.len(), 2);
+                assert!(parts.iter().all(|part| part.1 >= MIN_PIT_EDGE_COLUMNS));
+            }
+            let drawn: usize = parts.iter().map(|part| part.1).sum();
+            assert!(drawn <= segment.columns);
+        }
+        assert!(pits > 0);
+    }
+
+    #[test]
+    fn tile_index_picks_edges_and_top_row() {
+        assert_eq!(tile_index(0, 0, 4), TILE_TOP_LEFT);
+        assert_eq!(tile_index(0, 1, 4), TILE_TOP_MIDDLE);
+        assert_eq!(tile_index(0, 3, 4), TILE_TOP_RIGHT);
+        assert_eq!(tile_index(1, 0, 4), TILE_FILL_LEFT);
+        assert_eq!(tile_index(1, 2, 4), TILE_FILL_MIDDLE);
+        assert_eq!(tile_index(1, 3, 4), TILE_FILL_RIGHT);
+    }
 }
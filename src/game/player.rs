@@ -1,15 +1,23 @@
 #![allow(dead_code)]
-use bevy::prelude::*;
-use bevy_asset_loader::AssetCollection;
+use bevy::{core::FixedTimesteps, prelude::*, transform::TransformSystem, utils::HashSet};
 use heron::prelude::*;
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::{GameConfigAsset, GameConfigController};
+use super::{
+    animation::{Animation, AnimationClip, Animations},
+    config::ActiveConfig,
+    contacts::{dispatch_contacts, player_layers, WorldContactEvent},
+    dash::PlayerDash,
+    difficulty::Difficulty,
+    health::Health,
+    timestep::{overstep, FixedUpdate, SteppedTime, TimeScale},
+    view::ViewSize,
+    GameSettings, GameState, RunElement,
+};
 
-use super::{GameSettings, GameState};
-
-pub const PLAYER_SPEED: f32 = 3.0;
-pub const PLAYER_JUMP_FORCE: f32 = 30.0;
+/// Extra speed when steering left or right
+pub const PLAYER_STEER_SPEED: f32 = 180.0;
 
 const SPRITE_SIZE: f32 = 150.0;
 
@@ -20,19 +28,27 @@ const LIGHTNESS_SELECTED: f32 = 0.7;
 const ALPHA: f32 = 0.92;
 
 const SHOWCASE_TIMER_SECS: f32 = 3.0;
+/// How far the feet can sink into a platform and still stand on it
+const GROUND_TOLERANCE: f32 = 4.0;
 
-#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug)]
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
 pub enum PlayerAction {
     Pause,
     Jump,
     MoveLeft,
     MoveRight,
+    Dash,
 }
 
-#[derive(AssetCollection)]
-pub struct PlayerAssets {
-    #[asset(path = "player/2BlueWizardIdle/Chara - BlueIdle00001.png")]
-    player: Handle<Image>,
+impl PlayerAction {
+    /// Every action, in the order they are listed on the controls screen
+    pub const ALL: &'static [PlayerAction] = &[
+        PlayerAction::MoveLeft,
+        PlayerAction::MoveRight,
+        PlayerAction::Jump,
+        PlayerAction::Dash,
+        PlayerAction::Pause,
+    ];
 }
 
 pub struct PlayerPlugin;
@@ -40,101 +56,349 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(startup_player))
-            .add_system(player_movement);
+            .add_system_set(SystemSet::on_enter(GameState::GameLoading).with_system(startup_player))
+            .add_event::<PlayerHitEvent>()
+            .add_system(check_ground.after(dispatch_contacts))
+            .add_system(player_input)
+            .add_system(update_motion.after(check_ground))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                draw_player.before(TransformSystem::TransformPropagate),
+            )
+            .add_system_set_to_stage(
+                FixedUpdate,
+                SystemSet::on_update(GameState::InGame).with_system(player_movement),
+            );
     }
 }
 
 #[derive(Component, Debug)]
 pub struct PlayerSettings;
 
-fn get_resource_name(name: &str) -> String {
-    format!("player/2BlueWizardIdle/Chara - BlueIdle000{}.png", name)
+/// Drawn in place of the player body, between its last two physics
+/// positions so it moves smoothly when the frames and the steps don't line up
+#[derive(Component)]
+struct PlayerSprite {
+    previous: Vec2,
+    current: Vec2,
+}
+
+/// The player was hurt by something at `source`
+pub struct PlayerHitEvent {
+    pub source: Vec2,
+}
+
+/// Bodies the player can stand on
+#[derive(Component)]
+pub struct Ground;
+
+/// Timers of the jump input, in seconds
+#[derive(Component)]
+pub struct JumpState {
+    since_grounded: f32,
+    since_pressed: f32,
+    air_jumps: u32,
+    /// Rising from a jump that can still be cut short
+    rising: bool,
+    /// Jump was released since the last step
+    released: bool,
+}
+
+impl Default for JumpState {
+    fn default() -> Self {
+        JumpState {
+            since_grounded: f32::INFINITY,
+            since_pressed: f32::INFINITY,
+            air_jumps: 0,
+            rising: false,
+            released: false,
+        }
+    }
+}
+
+/// `Ground` bodies touching the player, and whether it stands on one of them
+#[derive(Component, Default)]
+pub struct GroundContacts {
+    touching: HashSet<Entity>,
+    standing: bool,
+}
+
+impl GroundContacts {
+    pub fn is_grounded(&self) -> bool {
+        self.standing
+    }
+}
+
+/// What the player is doing, selects the animation clip
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerMotion {
+    Grounded,
+    Running,
+    Airborne,
+    Dashing,
+    /// Knocked back by a hit
+    Hurt,
+}
+
+impl PlayerMotion {
+    fn clip(self) -> AnimationClip {
+        match self {
+            PlayerMotion::Grounded => AnimationClip::Idle,
+            PlayerMotion::Running => AnimationClip::Walk,
+            PlayerMotion::Airborne => AnimationClip::Jump,
+            PlayerMotion::Dashing => AnimationClip::Dash,
+            PlayerMotion::Hurt => AnimationClip::Jump,
+        }
+    }
 }
 
 fn startup_player(
     mut commands: Commands,
-    windows: Res<Windows>,
+    view: Res<ViewSize>,
     game_cfg: Res<GameSettings>,
-    asset_server: Res<AssetServer>,
-    assets: Res<Assets<GameConfigAsset>>,
-    q: Res<GameConfigController>,
+    animations: Res<Animations>,
+    cfg: Res<ActiveConfig>,
 ) {
-    let cfg = assets.get(q.handle.clone()).unwrap();
-    let window = windows.get_primary().unwrap();
-    let intit_player_pos_x = -(window.width() * cfg.player_initial_pos_x);
+    let intit_player_pos_x = cfg.player_start_x(view.width);
 
-    commands.insert_resource(Gravity::from(Vec3::new(
-        0.0,
-        -9.81 * cfg.gravity_multiplier,
-        0.0,
-    )));
+    commands.insert_resource(cfg.gravity());
 
+    let texture = animations.frame(AnimationClip::Idle, 0);
+    let sprite = Sprite {
+        custom_size: Some(Vec2::new(cfg.player_size_x, cfg.player_size_y)),
+        ..default()
+    };
+    let start = Vec2::new(intit_player_pos_x, 0.);
     commands
         .spawn_bundle(SpriteBundle {
-            texture: asset_server.load(get_resource_name("01").as_str()),
+            texture: texture.clone(),
             transform: Transform {
-                translation: Vec3::new(intit_player_pos_x, 0., 1.7),
+                translation: start.extend(1.7),
                 ..Default::default()
             },
-            sprite: Sprite {
-                custom_size: Some(Vec2::new(cfg.player_size_x, cfg.player_size_y)),
-                ..default()
-            },
+            sprite: sprite.clone(),
+            // `PlayerSprite` draws it
+            visibility: Visibility { is_visible: false },
             ..default()
         })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(SpriteBundle {
+                    texture,
+                    sprite,
+                    ..default()
+                })
+                .insert(PlayerSprite {
+                    previous: start,
+                    current: start,
+                });
+        })
         .insert_bundle(InputManagerBundle::<PlayerAction> {
             action_state: ActionState::default(),
-            input_map: game_cfg.player_ctrl.clone(),
+            input_map: game_cfg.player_ctrl(),
         })
         .insert(PlayerSettings)
+        .insert(PlayerMotion::Grounded)
+        .insert(PlayerDash::default())
+        .insert(Health::new(cfg.max_health, cfg.invulnerability_time))
+        .insert(GroundContacts::default())
+        .insert(JumpState::default())
+        .insert(Animation::new(AnimationClip::Idle))
+        .insert(RunElement)
         .insert(CollisionShape::Cuboid {
             half_extends: Vec2::new(cfg.player_box_size_x, cfg.player_box_size_y).extend(0.),
             border_radius: None,
         })
+        .insert(player_layers())
         .insert(RotationConstraints::lock())
+        .insert(Velocity::default())
         .insert(RigidBody::Dynamic);
+}
 
-    commands
-        .spawn_bundle(SpriteBundle {
-            texture: asset_server.load("DebugPixel.png"),
-            transform: Transform {
-                translation: Vec3::new(intit_player_pos_x, -200., 1.7),
-                ..Default::default()
-            },
-            ..default()
-        })
-        .insert(CollisionShape::Cuboid {
-            half_extends: Vec2::new(200., 50.).extend(0.),
-            border_radius: None,
-        })
-        .insert(RotationConstraints::lock())
-        .insert(RigidBody::Static);
+/// Copy the look of the body to its `PlayerSprite` and place it between the
+/// last two physics positions, the physics have moved the body by now
+fn draw_player(
+    timesteps: Res<FixedTimesteps>,
+    stepped: Res<SteppedTime>,
+    bodies: Query<(&Transform, &Sprite, &Handle<Image>, &Children), With<PlayerSettings>>,
+    mut sprites: Query<
+        (
+            &mut PlayerSprite,
+            &mut Transform,
+            &mut Sprite,
+            &mut Handle<Image>,
+        ),
+        Without<PlayerSettings>,
+    >,
+) {
+    for (body, body_sprite, body_texture, children) in bodies.iter() {
+        let position = body.translation.truncate();
+        for child in children.iter() {
+            let (mut drawn, mut transform, mut sprite, mut texture) = match sprites.get_mut(*child)
+            {
+                Ok(drawn) => drawn,
+                Err(_) => continue,
+            };
+            if stepped.delta_seconds() > 0. {
+                drawn.previous = drawn.current;
+                drawn.current = position;
+            } else if position != drawn.current {
+                // Moved outside of the steps, jump there
+                drawn.previous = position;
+                drawn.current = position;
+            }
+            let rendered = drawn.previous.lerp(drawn.current, overstep(&timesteps));
+            transform.translation = (rendered - position).extend(0.);
+            *sprite = body_sprite.clone();
+            *texture = body_texture.clone();
+        }
+    }
 }
 
-fn player_movement(
-    input: Query<&ActionState<PlayerAction>, With<PlayerSettings>>,
-    mut query: Query<(&mut PlayerSettings, &mut Sprite, &mut Transform)>,
+/// Top or bottom of a cuboid shape centered on `y`
+fn cuboid_edge(shape: &CollisionShape, y: f32, sign: f32) -> f32 {
+    match shape {
+        CollisionShape::Cuboid { half_extends, .. } => y + sign * half_extends.y,
+        _ => y,
+    }
+}
+
+/// Only the platforms the player lands on count, touching their side
+/// doesn't give the jump back
+fn check_ground(
+    mut world: EventReader<WorldContactEvent>,
+    ground: Query<(&Transform, &CollisionShape), With<Ground>>,
+    mut player: Query<(&Transform, &CollisionShape, &mut GroundContacts), With<PlayerSettings>>,
+) {
+    let (transform, shape, mut contacts) = match player.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    for contact in world.iter() {
+        if ground.get(contact.entity).is_err() {
+            continue;
+        }
+        if contact.started {
+            contacts.touching.insert(contact.entity);
+        } else {
+            contacts.touching.remove(&contact.entity);
+        }
+    }
+    // Despawned bodies never send the stopped event
+    contacts
+        .touching
+        .retain(|entity| ground.get(*entity).is_ok());
+    // A contact that started on a side counts once the player is over the top
+    let feet = cuboid_edge(shape, transform.translation.y, -1.);
+    let standing = contacts.touching.iter().any(|entity| {
+        let (ground_transform, ground_shape) = ground.get(*entity).unwrap();
+        feet >= cuboid_edge(ground_shape, ground_transform.translation.y, 1.) - GROUND_TOLERANCE
+    });
+    contacts.standing = standing;
+}
+
+/// Input edges are caught every frame, the steps may skip or repeat a frame
+pub fn player_input(
+    mut query: Query<(&ActionState<PlayerAction>, &mut JumpState), With<PlayerSettings>>,
     mut game_state: ResMut<State<GameState>>,
-    // mut camera: Query<(&Camera, &mut Transform)>,
 ) {
-    if let Ok(action) = input.get_single() {
+    if let Ok((action, mut jump)) = query.get_single_mut() {
+        // Leaving the pause is handled by the overlay
+        if game_state.current() != &GameState::InGame {
+            return;
+        }
         if action.just_pressed(PlayerAction::Pause) {
-            game_state.set(GameState::MainMenu).unwrap();
+            // Nothing else is queued yet, `detect_death` runs after it
+            game_state.push(GameState::Paused).unwrap();
             return;
         }
-        for (_player, mut _sprite, mut transform) in query.iter_mut() {
-            if game_state.current().eq(&GameState::InGame) {
-                if action.pressed(PlayerAction::MoveLeft) {
-                    transform.translation.x -= PLAYER_SPEED;
-                }
-                if action.pressed(PlayerAction::MoveRight) {
-                    transform.translation.x += PLAYER_SPEED;
-                }
-                if action.just_pressed(PlayerAction::Jump) {
-                    transform.translation.y += PLAYER_JUMP_FORCE;
-                }
-                transform.translation.x += PLAYER_SPEED;
+        if action.just_pressed(PlayerAction::Jump) {
+            jump.since_pressed = 0.;
+        }
+        if action.just_released(PlayerAction::Jump) {
+            jump.released = true;
+        }
+    }
+}
+
+fn player_movement(
+    time_scale: Res<TimeScale>,
+    difficulty: Res<Difficulty>,
+    cfg: Res<ActiveConfig>,
+    mut query: Query<
+        (
+            &ActionState<PlayerAction>,
+            &GroundContacts,
+            &PlayerMotion,
+            &mut JumpState,
+            &mut Velocity,
+        ),
+        With<PlayerSettings>,
+    >,
+) {
+    let delta = time_scale.step();
+    for (action, contacts, motion, mut jump, mut velocity) in query.iter_mut() {
+        // The dash and the knockback drive the horizontal speed while they last
+        if !matches!(motion, PlayerMotion::Dashing | PlayerMotion::Hurt) {
+            let mut speed = difficulty.run_speed;
+            if action.pressed(PlayerAction::MoveLeft) {
+                speed -= PLAYER_STEER_SPEED;
+            }
+            if action.pressed(PlayerAction::MoveRight) {
+                speed += PLAYER_STEER_SPEED;
+            }
+            velocity.linear.x = speed;
+        }
+
+        if contacts.is_grounded() && !jump.rising {
+            jump.since_grounded = 0.;
+            jump.air_jumps = cfg.air_jumps;
+        }
+
+        // Coyote time lets a late press count as if still on the ledge,
+        // the buffer keeps an early press until the player lands
+        // when there are no air jumps left
+        let buffered = jump.since_pressed <= cfg.jump_buffer_time;
+        let coyote = jump.since_grounded <= cfg.coyote_time;
+        if buffered && (coyote || jump.air_jumps > 0) {
+            if !coyote {
+                jump.air_jumps -= 1;
+            }
+            velocity.linear.y = cfg.jump_speed;
+            jump.since_pressed = f32::INFINITY;
+            jump.since_grounded = f32::INFINITY;
+            jump.rising = true;
+        } else if jump.rising && velocity.linear.y <= 0. {
+            jump.rising = false;
+        } else if jump.rising && jump.released {
+            // Releasing early cuts the arc
+            velocity.linear.y *= cfg.jump_cut_multiplier;
+            jump.rising = false;
+        }
+        jump.released = false;
+        jump.since_pressed += delta;
+        jump.since_grounded += delta;
+    }
+}
+
+fn update_motion(
+    game_state: Res<State<GameState>>,
+    mut query: Query<(&GroundContacts, &mut PlayerMotion, &mut Animation), With<PlayerSettings>>,
+) {
+    for (contacts, mut motion, mut animation) in query.iter_mut() {
+        // Dashing and Hurt are left by their own systems
+        if !matches!(*motion, PlayerMotion::Dashing | PlayerMotion::Hurt) {
+            let next = if !contacts.is_grounded() {
+                PlayerMotion::Airborne
+            } else if game_state.current() == &GameState::InGame {
+                PlayerMotion::Running
+            } else {
+                PlayerMotion::Grounded
+            };
+            if *motion != next {
+                *motion = next;
             }
         }
+        animation.play(motion.clip());
     }
 }
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+use super::{arg_value, config::ActiveConfig, GameState};
+
+const SEED_ARG: &str = "--seed";
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let seed_arg = seed_from_args();
+        app.insert_resource(SeedOverride(seed_arg))
+            .insert_resource(GameRng::from_seed(seed_arg.unwrap_or_else(rand::random)))
+            .add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(reset_rng))
+            .add_system_set(SystemSet::on_enter(GameState::GameLoading).with_system(reset_rng));
+    }
+}
+
+/// Seed forced from the command line, it takes priority over `config.ron`
+pub struct SeedOverride(pub Option<u64>);
+
+/// What a stream of `GameRng` is drawn for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RngStream {
+    Platforms,
+    Enemies,
+    Pickups,
+    Obstacles,
+}
+
+/// Seed of the level generation. Each segment of the track and each spawner
+/// draws from its own stream, so the same seed gives the same track whatever
+/// the frame rate, the window size or the number of draws made elsewhere
+pub struct GameRng {
+    seed: u64,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Generator of `stream` for the segment number `index` of the run
+    pub fn stream(&self, stream: RngStream, index: u64) -> StdRng {
+        let mut key = [0; 32];
+        key[..8].copy_from_slice(&self.seed.to_le_bytes());
+        key[8..16].copy_from_slice(&(stream as u64).to_le_bytes());
+        key[16..24].copy_from_slice(&index.to_le_bytes());
+        StdRng::from_seed(key)
+    }
+}
+
+fn seed_from_args() -> Option<u64> {
+    seed_from(std::env::args().skip(1))
+}
+
+fn seed_from(args: impl Iterator<Item = String>) -> Option<u64> {
+    arg_value(args, SEED_ARG)
+}
+
+fn reset_rng(mut rng: ResMut<GameRng>, seed_arg: Res<SeedOverride>, cfg: Res<ActiveConfig>) {
+    let seed = seed_arg.0.or(cfg.seed).unwrap_or_else(rand::random);
+    *rng = GameRng::from_seed(seed);
+    info!("Running with seed {}", seed);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn seed_as_separate_or_joined_argument() {
+        assert_eq!(seed_from(args(&["--seed", "42"])), Some(42));
+        assert_eq!(seed_from(args(&["--headless", "--seed=7"])), Some(7));
+    }
+
+    #[test]
+    fn invalid_or_missing_seed_is_ignored() {
+        assert_eq!(seed_from(args(&[])), None);
+        assert_eq!(seed_from(args(&["--seed"])), None);
+        assert_eq!(seed_from(args(&["--seed", "abc"])), None);
+        assert_eq!(seed_from(args(&["--seed=abc", "--seed", "3"])), Some(3));
+    }
+
+    fn draws(rng: &GameRng, stream: RngStream, index: u64) -> Vec<u32> {
+        let mut rng = rng.stream(stream, index);
+        (0..16).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_streams() {
+        let first = GameRng::from_seed(1234);
+        let second = GameRng::from_seed(1234);
+        assert_eq!(
+            draws(&first, RngStream::Enemies, 5),
+            draws(&second, RngStream::Enemies, 5)
+        );
+        assert_ne!(
+            draws(&first, RngStream::Enemies, 5),
+            draws(&GameRng::from_seed(1235), RngStream::Enemies, 5)
+        );
+    }
+
+    #[test]
+    fn streams_are_independent() {
+        let rng = GameRng::from_seed(1234);
+        let enemies = draws(&rng, RngStream::Enemies, 5);
+        assert_ne!(enemies, draws(&rng, RngStream::Pickups, 5));
+        assert_ne!(enemies, draws(&rng, RngStream::Enemies, 6));
+        // Drawing from one stream leaves the others untouched
+        let mut pickups = rng.stream(RngStream::Pickups, 5);
+        let _: u64 = pickups.gen();
+        assert_eq!(enemies, draws(&rng, RngStream::Enemies, 5));
+    }
+}
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+
+use super::{enviroment::Floor, timestep::SteppedTime, GameState};
+
+pub const PIXELS_PER_METER: f32 = 50.0;
+/// Seconds between pickups to keep the combo alive
+const COMBO_WINDOW_SECS: f32 = 2.0;
+/// Pickups in a row needed to raise the multiplier
+const COMBO_STEP: u32 = 5;
+const MAX_MULTIPLIER: u32 = 5;
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Score::default())
+            .add_event::<PickupEvent>()
+            .add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(reset_score))
+            .add_system_set(SystemSet::on_enter(GameState::GameLoading).with_system(reset_score))
+            .add_system_set(
+                SystemSet::on_update(GameState::InGame)
+                    .with_system(track_score)
+                    .with_system(collect_pickups.before(track_score)),
+            );
+    }
+}
+
+/// Sent when the player grabs something worth points
+pub struct PickupEvent {
+    pub points: u32,
+}
+
+/// Progress of the current run
+#[derive(Default)]
+pub struct Score {
+    /// Meters advanced since the start of the run
+    pub distance: f32,
+    /// Seconds played
+    pub time: f32,
+    pub points: u32,
+    pub pickups: u32,
+    /// Pickups grabbed in a row
+    pub combo: u32,
+    /// Points earned from pickups, already multiplied
+    bonus: u32,
+    combo_timer: f32,
+}
+
+impl Score {
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.combo / COMBO_STEP).min(MAX_MULTIPLIER)
+    }
+}
+
+fn reset_score(mut score: ResMut<Score>) {
+    *score = Score::default();
+}
+
+fn collect_pickups(mut score: ResMut<Score>, mut pickups: EventReader<PickupEvent>) {
+    for pickup in pickups.iter() {
+        score.pickups += 1;
+        score.combo += 1;
+        score.combo_timer = COMBO_WINDOW_SECS;
+        score.bonus += pickup.points * score.multiplier();
+    }
+}
+
+fn track_score(
+    time: Res<SteppedTime>,
+    mut score: ResMut<Score>,
+    floor: Query<&Transform, With<Floor>>,
+) {
+    score.time += time.delta_seconds();
+    // The floor starts every run at x = 0 and advances with the scroll
+    if let Ok(floor) = floor.get_single() {
+        score.distance = floor.translation.x / PIXELS_PER_METER;
+    }
+
+    if score.combo > 0 {
+        score.combo_timer -= time.delta_seconds();
+        if score.combo_timer <= 0. {
+            score.combo = 0;
+        }
+    }
+    score.points = score.distance as u32 + score.bonus;
+}
@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{mainmenu::MainMenuState, player::PlayerAction, storage, GameSettings};
+
+const SETTINGS_FILE: &str = "settings.ron";
+/// Bump when the layout of `GameSettings` changes, missing fields
+/// of older files are filled with the defaults
+const SETTINGS_VERSION: u32 = 2;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        // Saved once when leaving a screen that edits them, not on every
+        // frame of a slider drag
+        app.add_system_set(SystemSet::on_exit(MainMenuState::Options).with_system(save_settings))
+            .add_system_set(SystemSet::on_exit(MainMenuState::Controls).with_system(save_settings))
+            .add_system(apply_window);
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SettingsFile {
+    version: u32,
+    settings: GameSettings,
+}
+
+impl GameSettings {
+    pub fn load() -> Self {
+        match storage::load::<SettingsFile>(SETTINGS_FILE) {
+            Some(mut file) if file.version <= SETTINGS_VERSION => {
+                file.settings.bind_new_actions();
+                file.settings
+            }
+            Some(file) => {
+                warn!(
+                    "Settings version {} is newer than {}, using defaults",
+                    file.version, SETTINGS_VERSION
+                );
+                Self::default()
+            }
+            None => Self::default(),
+        }
+    }
+
+    /// Actions added after the file was saved get their default bindings
+    fn bind_new_actions(&mut self) {
+        let defaults = Self::default().bindings;
+        for action in PlayerAction::ALL {
+            if !self.bindings.iter().any(|(bound, _)| bound == action) {
+                self.bindings
+                    .extend(defaults.iter().filter(|(bound, _)| bound == action));
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        storage::save(
+            SETTINGS_FILE,
+            &SettingsFile {
+                version: SETTINGS_VERSION,
+                settings: self.clone(),
+            },
+        );
+    }
+}
+
+fn save_settings(settings: Res<GameSettings>) {
+    settings.save();
+}
+
+/// Keep the primary window in sync with the fullscreen and resolution options
+fn apply_window(settings: Res<GameSettings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+    let window = match windows.get_primary_mut() {
+        Some(window) => window,
+        None => return,
+    };
+    let mode = settings.window_mode();
+    if window.mode() != mode {
+        window.set_mode(mode);
+    }
+    let (width, height) = settings.resolution;
+    if window.requested_width() != width || window.requested_height() != height {
+        window.set_resolution(width, height);
+    }
+}
@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+const APP_DIR: &str = "bevy-runner";
+
+/// Read a RON entry from the user storage,
+/// missing or corrupted entries are reported as `None`
+pub fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let content = backend::read(name)?;
+    match ron::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Ignoring corrupted {}: {}", name, e);
+            None
+        }
+    }
+}
+
+/// Write a value as RON to the user storage
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    let content = match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::new()) {
+        Ok(content) => content,
+        Err(e) => {
+            error!("Could not serialize {}: {}", name, e);
+            return;
+        }
+    };
+    if let Err(e) = backend::write(name, &content) {
+        error!("Could not write {}: {}", name, e);
+    }
+}
+
+/// Files inside the user data directory
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::{fs, path::PathBuf};
+
+    use super::APP_DIR;
+
+    fn data_path(name: &str) -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join(APP_DIR).join(name))
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        fs::read_to_string(data_path(name)?).ok()
+    }
+
+    pub fn write(name: &str, content: &str) -> Result<(), String> {
+        let path = data_path(name).ok_or("no data directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, content).map_err(|e| e.to_string())
+    }
+}
+
+/// Entries of the browser localStorage
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use web_sys::Storage;
+
+    use super::APP_DIR;
+
+    fn local_storage() -> Option<Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn key(name: &str) -> String {
+        format!("{}/{}", APP_DIR, name)
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        local_storage()?.get_item(&key(name)).ok()?
+    }
+
+    pub fn write(name: &str, content: &str) -> Result<(), String> {
+        local_storage()
+            .ok_or("no localStorage")?
+            .set_item(&key(name), content)
+            .map_err(|e| format!("{:?}", e))
+    }
+}
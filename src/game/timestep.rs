@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use bevy::{
+    core::{FixedTimestep, FixedTimesteps},
+    ecs::schedule::StageLabel,
+    prelude::*,
+};
+use heron::{PhysicsSteps, PhysicsTime};
+
+use super::GameState;
+
+/// Seconds between two gameplay steps
+pub const TIMESTEP: f64 = 1. / 60.;
+const TIMESTEP_LABEL: &str = "gameplay";
+
+/// Stage running the gameplay at `TIMESTEP`, zero or more times per frame
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub struct FixedUpdate;
+
+/// Speed of the gameplay against real time, below 1 in slow motion.
+/// The physics follow it, the fixed steps advance by `step` seconds
+pub struct TimeScale(pub f32);
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale(1.)
+    }
+}
+
+impl TimeScale {
+    /// Gameplay seconds covered by one fixed step
+    pub fn step(&self) -> f32 {
+        TIMESTEP as f32 * self.0
+    }
+}
+
+/// Seconds of fixed steps run during this frame, read by the gameplay
+/// timers of `Update` so they follow the steps instead of the wall clock
+#[derive(Default)]
+pub struct SteppedTime(f32);
+
+impl SteppedTime {
+    pub fn delta_seconds(&self) -> f32 {
+        self.0
+    }
+
+    pub fn delta(&self) -> Duration {
+        Duration::from_secs_f32(self.0)
+    }
+}
+
+pub struct TimestepPlugin;
+
+impl Plugin for TimestepPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_stage_before(
+            CoreStage::Update,
+            FixedUpdate,
+            SystemStage::parallel()
+                .with_run_criteria(FixedTimestep::step(TIMESTEP).with_label(TIMESTEP_LABEL)),
+        )
+        // One physics step per frame, `step_physics` sizes it to the fixed steps of the frame
+        .insert_resource(PhysicsSteps::every_frame(Duration::from_secs_f64(TIMESTEP)))
+        .insert_resource(TimeScale::default())
+        .insert_resource(SteppedTime::default())
+        .add_system_to_stage(FixedUpdate, count_step)
+        .add_system_to_stage(CoreStage::Last, reset_stepped_time)
+        // The physics step runs after `Update`
+        .add_system_to_stage(CoreStage::Update, step_physics);
+    }
+}
+
+/// Fraction of a step elapsed since the last gameplay step, used to
+/// interpolate what is rendered between two steps
+pub fn overstep(timesteps: &FixedTimesteps) -> f32 {
+    timesteps
+        .get(TIMESTEP_LABEL)
+        .map_or(0., |state| state.overstep_percentage() as f32)
+}
+
+/// Non repeating timer of `secs` that has already run out
+pub fn finished_timer(secs: f32) -> Timer {
+    let mut timer = Timer::from_seconds(secs, false);
+    timer.tick(Duration::from_secs_f32(secs));
+    timer
+}
+
+fn count_step(mut stepped: ResMut<SteppedTime>) {
+    stepped.0 += TIMESTEP as f32;
+}
+
+fn reset_stepped_time(mut stepped: ResMut<SteppedTime>) {
+    stepped.0 = 0.;
+}
+
+/// Advance the physics by the fixed steps run during this frame, so they
+/// never drift apart from the gameplay whatever the frame rate is
+fn step_physics(
+    game_state: Res<State<GameState>>,
+    stepped: Res<SteppedTime>,
+    scale: Res<TimeScale>,
+    mut physics_time: ResMut<PhysicsTime>,
+) {
+    // The world stays still while the run is stopped
+    let steps = if matches!(
+        game_state.current(),
+        GameState::Paused | GameState::GameOver
+    ) {
+        0.
+    } else {
+        stepped.0 / TIMESTEP as f32
+    };
+    physics_time.set_scale(steps * scale.0);
+}
@@ -47,7 +47,7 @@ pub fn hide_text(
         commands.entity(entity).remove::<Animator<Text>>();
         commands.entity(entity).insert(Animator::new(tween_text));
     }
-    transition_state.set(TransitionStateText::Hide).unwrap();
+    let _ = transition_state.overwrite_set(TransitionStateText::Hide);
 }
 
 pub fn show_text(
@@ -70,7 +70,8 @@ pub fn show_text(
         commands.entity(entity).remove::<Animator<Text>>();
         commands.entity(entity).insert(Animator::new(tween_text));
     }
-    transition_state.set(TransitionStateText::Show).unwrap();
+    // Leaving a screen and entering the next one happens in the same frame
+    let _ = transition_state.overwrite_set(TransitionStateText::Show);
 }
 
 pub fn transition_update(
@@ -86,7 +87,8 @@ pub fn transition_update(
                     visibility.is_visible = t.show;
                 }
             }
-            transition_state.set(TransitionStateText::Idle).unwrap();
+            // A new transition may already be queued for this frame
+            let _ = transition_state.set(TransitionStateText::Idle);
         }
         TransitionStateText::Hide => {
             for (animator, mut visibility, mut t) in query_text.iter_mut() {
@@ -96,7 +98,7 @@ pub fn transition_update(
                     visibility.is_visible = t.show;
                 }
             }
-            transition_state.set(TransitionStateText::Idle).unwrap();
+            let _ = transition_state.set(TransitionStateText::Idle);
         }
         TransitionStateText::Idle => {}
     }
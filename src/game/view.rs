@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use bevy_parallax::ParallaxCameraComponent;
+
+pub struct ViewPlugin;
+
+impl Plugin for ViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PreUpdate, follow_window);
+    }
+}
+
+/// Size of the visible area in pixels, gameplay reads it instead of the window
+/// so it also runs headless, where there is no window at all
+#[derive(Debug, Clone, Copy)]
+pub struct ViewSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ViewSize {
+    pub fn new((width, height): (f32, f32)) -> Self {
+        ViewSize { width, height }
+    }
+}
+
+/// Despawns the run entities that scrolled out of the view on the left,
+/// `entities` gives the right edge of each one
+pub fn despawn_scrolled_out(
+    commands: &mut Commands,
+    view: &ViewSize,
+    camera: &Query<&Transform, With<ParallaxCameraComponent>>,
+    entities: impl Iterator<Item = (Entity, f32)>,
+) {
+    let camera = match camera.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let left_edge = camera.translation.x - view.width / 2.;
+
+    for (entity, right_edge) in entities {
+        if right_edge < left_edge {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn follow_window(windows: Option<Res<Windows>>, mut view: ResMut<ViewSize>) {
+    let window = match windows.as_ref().and_then(|windows| windows.get_primary()) {
+        Some(window) => window,
+        None => return,
+    };
+    if view.width != window.width() || view.height != window.height() {
+        view.width = window.width();
+        view.height = window.height();
+    }
+}
@@ -2,31 +2,59 @@
 #[cfg(feature = "ui-debug")]
 use bevy_inspector_egui::WorldInspectorPlugin;
 
-use bevy::{asset::AssetServerSettings, prelude::*, reflect::TypeUuid, window::WindowMode};
+use bevy::{asset::AssetServerSettings, prelude::*, reflect::TypeUuid};
 use bevy_asset_loader::{AssetCollection, AssetLoader};
 use bevy_asset_ron::*;
 use bevy_parallax::ParallaxPlugin;
 use bevy_tweening::TweeningPlugin;
-use heron::PhysicsPlugin;
+use heron::{rapier_plugin::RapierPlugin, PhysicsPlugin};
 use leafwing_input_manager::prelude::*;
 
 mod game;
 
 use game::{
+    animation::{AnimationPlugin, Animations},
     audio::AmbientAudioPlugin,
+    config::ConfigPlugin,
+    contacts::ContactsPlugin,
+    controls::ControlsPlugin,
+    dash::DashPlugin,
+    despawn_run,
+    difficulty::{DifficultyAsset, DifficultyPlugin},
+    enemy::EnemyPlugin,
     enviroment::{Enviroment, EnviromentAssets},
-    mainmenu::MainMenu,
-    player::{PlayerAction, PlayerAssets, PlayerPlugin},
+    gameover::GameOverPlugin,
+    headless::{headless_from_args, HeadlessPlugin, HeadlessPlugins, HEADLESS_VIEW_SIZE},
+    health::HealthPlugin,
+    hud::HudPlugin,
+    leaderboard::LeaderboardPlugin,
+    mainmenu::{CreditsAsset, MainMenu},
+    obstacle::{ObstacleAsset, ObstaclePlugin},
+    options::OptionsPlugin,
+    pause::PausePlugin,
+    pickup::{PickupAsset, PickupPlugin},
+    platform::PlatformPlugin,
+    player::{PlayerAction, PlayerPlugin},
+    rng::RngPlugin,
+    score::ScorePlugin,
+    settings::SettingsPlugin,
     splash::load_splash,
+    start_run,
+    timestep::TimestepPlugin,
     transition::TransitionPlugin,
+    view::{ViewPlugin, ViewSize},
     GameSettings, GameState,
 };
 
+#[derive(AssetCollection)]
 pub struct GameConfigController {
+    #[asset(path = "config.ron")]
     handle: Handle<GameConfigAsset>,
 }
 
-#[derive(serde::Deserialize, TypeUuid)]
+/// Missing fields take their value from `Default`, see `game::config` for the valid ranges
+#[derive(serde::Deserialize, TypeUuid, Clone)]
+#[serde(default)]
 #[uuid = "b7f64775-6e72-4080-9ced-167607f1f0b2"]
 pub struct GameConfigAsset {
     pub gravity_multiplier: f32,
@@ -37,6 +65,60 @@ pub struct GameConfigAsset {
     pub player_box_size_y: f32,
     pub audio_volume: f32,
     pub floor_multiplier: f32,
+    pub animation_fps: f32,
+    /// Vertical speed given by a jump
+    pub jump_speed: f32,
+    /// Applied to the rising speed when Jump is released early
+    pub jump_cut_multiplier: f32,
+    /// Seconds after leaving a ledge where a jump is still allowed
+    pub coyote_time: f32,
+    /// Seconds a Jump pressed in the air is kept until landing
+    pub jump_buffer_time: f32,
+    /// Extra jumps in the air, 0 disables double jump
+    pub air_jumps: u32,
+    /// Horizontal distance the player can move without the camera following
+    pub camera_dead_zone: f32,
+    /// How far ahead of the player the camera aims at full speed
+    pub camera_look_ahead: f32,
+    /// Rate at which the camera catches up, higher is snappier
+    pub camera_smoothing: f32,
+    /// Hits the player can take, falling off the track always kills
+    pub max_health: u32,
+    /// Seconds without taking damage after a hit
+    pub invulnerability_time: f32,
+    /// Speeds the player is pushed away with when hit
+    pub knockback_speed: f32,
+    pub knockback_lift: f32,
+    pub seed: Option<u64>,
+}
+
+impl Default for GameConfigAsset {
+    fn default() -> Self {
+        GameConfigAsset {
+            gravity_multiplier: 70.0,
+            player_initial_pos_x: 0.35,
+            player_size_x: 170.0,
+            player_size_y: 170.0,
+            player_box_size_x: 20.0,
+            player_box_size_y: 45.0,
+            audio_volume: 1.0,
+            floor_multiplier: 0.35,
+            animation_fps: 24.0,
+            jump_speed: 520.0,
+            jump_cut_multiplier: 0.5,
+            coyote_time: 0.1,
+            jump_buffer_time: 0.12,
+            air_jumps: 1,
+            camera_dead_zone: 150.0,
+            camera_look_ahead: 300.0,
+            camera_smoothing: 5.0,
+            max_health: 3,
+            invulnerability_time: 1.5,
+            knockback_speed: 300.0,
+            knockback_lift: 350.0,
+            seed: None,
+        }
+    }
 }
 
 #[derive(AssetCollection)]
@@ -55,56 +137,114 @@ pub struct GlobalUIAssets {
     pixel_font: Handle<Font>,
     #[asset(path = "fonts/tomorrow_night.ttf")]
     tomorrow_font: Handle<Font>,
+    #[asset(path = "game.credits.ron")]
+    credits: Handle<CreditsAsset>,
 }
 
 fn main() {
     let mut app = App::new();
+    let headless = headless_from_args();
 
     AssetLoader::new(GameState::Splash)
-        .continue_to_state(GameState::MainMenu)
+        // `game::config` moves on to the main menu once the config is valid
+        .continue_to_state(GameState::SplashEnd)
+        .with_collection::<GameConfigController>()
         .with_collection::<ImageAssets>()
         .with_collection::<EnviromentAssets>()
-        .with_collection::<PlayerAssets>()
         .with_collection::<GlobalUIAssets>()
+        .with_collection::<Animations>()
         .build(&mut app);
 
-    app.insert_resource(WindowDescriptor {
-        title: "Bevy Infinity Runner".to_string(),
-        mode: WindowMode::BorderlessFullscreen,
-        ..Default::default()
-    })
-    .insert_resource(AssetServerSettings {
+    app.insert_resource(AssetServerSettings {
         watch_for_changes: true,
         ..default()
     })
-    .insert_resource(Msaa { samples: 4 })
-    .insert_resource(ClearColor(Color::rgb(
-        0.462_745_1,
-        0.576_470_6,
-        0.701_960_8,
-    )))
-    .insert_resource(GameSettings::default())
     .add_state(GameState::Splash)
     .add_system_set(SystemSet::on_enter(GameState::Splash).with_system(load_splash))
-    .add_plugins(DefaultPlugins)
-    .add_plugin(RonAssetPlugin::<GameConfigAsset>::new(&["ron"]))
-    .add_startup_system(load_config)
-    .add_plugin(TweeningPlugin)
-    .add_plugin(TransitionPlugin)
-    .add_plugin(ParallaxPlugin)
-    .add_plugin(PhysicsPlugin::default())
-    .add_plugin(InputManagerPlugin::<PlayerAction>::default())
-    .add_plugin(MainMenu)
-    .add_plugin(Enviroment)
-    .add_plugin(AmbientAudioPlugin)
-    .add_plugin(PlayerPlugin);
+    .add_system_set(SystemSet::on_exit(GameState::InGame).with_system(despawn_run))
+    .add_system_set(SystemSet::on_update(GameState::GameLoading).with_system(start_run));
+
+    if headless {
+        // A fixed view and the default settings, the saved ones are never touched
+        app.insert_resource(ViewSize::new(HEADLESS_VIEW_SIZE))
+            .insert_resource(GameSettings::default())
+            .add_plugins(MinimalPlugins)
+            .add_plugins(HeadlessPlugins);
+    } else {
+        let settings = GameSettings::load();
+        app.insert_resource(WindowDescriptor {
+            title: "Bevy Infinity Runner".to_string(),
+            width: settings.resolution.0,
+            height: settings.resolution.1,
+            mode: settings.window_mode(),
+            ..Default::default()
+        })
+        .insert_resource(Msaa { samples: 4 })
+        .insert_resource(ClearColor(Color::rgb(
+            0.462_745_1,
+            0.576_470_6,
+            0.701_960_8,
+        )))
+        .insert_resource(ViewSize::new(settings.resolution))
+        .insert_resource(settings)
+        .add_plugins(DefaultPlugins)
+        // Menus, screens and sound only make sense with a window
+        .add_plugin(TransitionPlugin)
+        .add_plugin(MainMenu)
+        .add_plugin(AmbientAudioPlugin)
+        .add_plugin(PausePlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(LeaderboardPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(OptionsPlugin)
+        .add_plugin(ControlsPlugin);
+    }
+
+    // The gameplay, shared by both modes
+    app.add_plugin(RonAssetPlugin::<GameConfigAsset>::new(&["ron"]))
+        // Matched before "ron" since the longest extension is tried first
+        .add_plugin(RonAssetPlugin::<CreditsAsset>::new(&["credits.ron"]))
+        .add_plugin(RonAssetPlugin::<DifficultyAsset>::new(&["difficulty.ron"]))
+        .add_plugin(RonAssetPlugin::<PickupAsset>::new(&["pickups.ron"]))
+        .add_plugin(RonAssetPlugin::<ObstacleAsset>::new(&["obstacles.ron"]))
+        .add_plugin(ConfigPlugin)
+        .add_plugin(ViewPlugin)
+        .add_plugin(TweeningPlugin)
+        .add_plugin(ParallaxPlugin);
+
+    // Before `TimestepPlugin`, which sets how the physics step
+    if headless {
+        // Skips the debug shapes `ui-debug` adds to `PhysicsPlugin`, they need the renderer
+        app.add_plugin(RapierPlugin);
+    } else {
+        app.add_plugin(PhysicsPlugin::default());
+    }
+
+    app.add_plugin(InputManagerPlugin::<PlayerAction>::default())
+        .add_plugin(TimestepPlugin)
+        .add_plugin(ContactsPlugin)
+        .add_plugin(Enviroment)
+        .add_plugin(AnimationPlugin)
+        .add_plugin(PlayerPlugin)
+        .add_plugin(DashPlugin)
+        .add_plugin(HealthPlugin)
+        .add_plugin(RngPlugin)
+        .add_plugin(DifficultyPlugin)
+        .add_plugin(PlatformPlugin)
+        .add_plugin(EnemyPlugin)
+        .add_plugin(PickupPlugin)
+        .add_plugin(ObstaclePlugin)
+        .add_plugin(ScorePlugin)
+        .add_plugin(GameOverPlugin);
+
+    if headless {
+        app.add_plugin(HeadlessPlugin);
+    }
 
     #[cfg(feature = "ui-debug")]
-    app.add_plugin(WorldInspectorPlugin::new());
+    if !headless {
+        app.add_plugin(WorldInspectorPlugin::new());
+    }
 
     app.run();
 }
-fn load_config(mut commands: Commands, asset_server: ResMut<AssetServer>) {
-    let handle = asset_server.load("config.ron");
-    commands.insert_resource(GameConfigController { handle });
-}